@@ -0,0 +1,321 @@
+//! The HTTP client shared by every network call. It carries the proxy and
+//! timeouts from settings, and `send` retries server errors and turns rate
+//! limit responses into `RepoError::RateLimited`. `get_cached` adds an on-disk
+//! response cache that revalidates with ETag/Last-Modified and falls back to
+//! the cached body when the network is unavailable.
+
+use chrono::{DateTime, Utc};
+use reqwest::header::{
+    HeaderMap, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RETRY_AFTER,
+};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+
+use crate::paths;
+use crate::repo::{load_settings, AppSettings, RepoError};
+
+pub const USER_AGENT: &str = concat!("RepoRead/", env!("CARGO_PKG_VERSION"));
+
+/// Attempts per request, including the first
+const MAX_ATTEMPTS: u32 = 3;
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+/// 5xx responses asking for a longer wait than this are returned instead of retried
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(10);
+
+fn shared() -> &'static RwLock<reqwest::Client> {
+    static CLIENT: OnceLock<RwLock<reqwest::Client>> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        // Fall back to a plain client rather than failing every request over a bad proxy setting
+        let client = build_client(&load_settings()).unwrap_or_else(|_| {
+            reqwest::Client::builder()
+                .user_agent(USER_AGENT)
+                .build()
+                .unwrap_or_default()
+        });
+        RwLock::new(client)
+    })
+}
+
+fn build_client(settings: &AppSettings) -> Result<reqwest::Client, RepoError> {
+    let mut builder = reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .connect_timeout(Duration::from_secs(settings.connect_timeout_secs.max(1)))
+        .read_timeout(Duration::from_secs(settings.read_timeout_secs.max(1)));
+
+    if let Some(proxy_url) = settings.proxy_url.as_deref().map(str::trim).filter(|u| !u.is_empty()) {
+        let proxy = reqwest::Proxy::all(proxy_url)
+            .map_err(|e| RepoError::InvalidUrl(format!("Invalid proxy URL {}: {}", proxy_url, e)))?
+            .no_proxy(settings.no_proxy.as_deref().and_then(reqwest::NoProxy::from_string));
+        builder = builder.proxy(proxy);
+    }
+
+    Ok(builder.build()?)
+}
+
+/// The shared client. Cloning is cheap; clones share one connection pool.
+pub fn client() -> reqwest::Client {
+    shared().read().unwrap().clone()
+}
+
+/// Rebuilds the shared client after the network settings changed
+pub fn configure(settings: &AppSettings) -> Result<(), RepoError> {
+    let client = build_client(settings)?;
+    *shared().write().unwrap() = client;
+    Ok(())
+}
+
+/// Sends `request`, retrying connection failures and 5xx responses with
+/// exponential backoff. Rate limit responses become `RepoError::RateLimited`;
+/// any other status is returned for the caller to interpret.
+pub async fn send(mut request: reqwest::RequestBuilder) -> Result<reqwest::Response, RepoError> {
+    let mut attempt = 1;
+    loop {
+        // Streaming bodies can't be cloned, so those requests only get one attempt
+        let next = if attempt < MAX_ATTEMPTS { request.try_clone() } else { None };
+        let backoff = RETRY_BASE_DELAY * 2u32.pow(attempt - 1);
+
+        let delay = match request.send().await {
+            Ok(response) => {
+                if let Some(error) = rate_limit_error(response.status(), response.headers()) {
+                    return Err(error);
+                }
+                if !response.status().is_server_error() || next.is_none() {
+                    return Ok(response);
+                }
+                match retry_after(response.headers()) {
+                    Some(wait) if wait > MAX_RETRY_AFTER => return Ok(response),
+                    Some(wait) => wait,
+                    None => backoff,
+                }
+            }
+            Err(e) if next.is_some() && (e.is_timeout() || e.is_connect()) => backoff,
+            Err(e) => return Err(e.into()),
+        };
+
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+        request = next.expect("only requests with a retry copy get here");
+    }
+}
+
+/// 429s, and 403s sent for a rate limit rather than missing access: ones
+/// with no requests left in the window, or asking to retry later as GitHub's
+/// secondary limits do
+pub fn rate_limit_error(status: StatusCode, headers: &HeaderMap) -> Option<RepoError> {
+    // GitHub and Gitea send the `x-` form, GitLab the bare one
+    let exhausted = ["x-ratelimit-remaining", "ratelimit-remaining"].iter().any(|name| {
+        headers
+            .get(*name)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.trim() == "0")
+    });
+
+    let limited = match status {
+        StatusCode::TOO_MANY_REQUESTS => true,
+        StatusCode::FORBIDDEN => exhausted || headers.contains_key(RETRY_AFTER),
+        _ => false,
+    };
+    if !limited {
+        return None;
+    }
+
+    let reset_at = headers
+        .get("x-ratelimit-reset")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .and_then(|epoch| DateTime::<Utc>::from_timestamp(epoch, 0))
+        .or_else(|| {
+            retry_after(headers).and_then(|wait| chrono::Duration::from_std(wait).ok().map(|d| Utc::now() + d))
+        });
+    Some(RepoError::RateLimited { reset_at })
+}
+
+/// `Retry-After` in either of its forms, delay-seconds or an HTTP date
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?;
+    (at.with_timezone(&Utc) - Utc::now()).to_std().ok()
+}
+
+/// Entries not revalidated for this long are dropped at startup
+const CACHE_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Headers that carry a credential: the standard ones and GitLab's token headers
+const CREDENTIAL_HEADERS: [&str; 4] = ["authorization", "cookie", "private-token", "job-token"];
+
+fn get_http_cache_dir() -> PathBuf {
+    paths::data_dir().join("http-cache")
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    fetched_at: DateTime<Utc>,
+    body: String,
+}
+
+/// Data that may have been served from the response cache. `stale` is set
+/// when the server couldn't be reached and `fetched_at` says how old it is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cached<T> {
+    pub data: T,
+    pub stale: bool,
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// Response of `get_cached`. A 304 comes back as 200 with the cached body.
+pub struct CachedResponse {
+    pub status: StatusCode,
+    /// Empty when the body came from the cache
+    pub headers: HeaderMap,
+    pub body: String,
+    pub stale: bool,
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl CachedResponse {
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, RepoError> {
+        Ok(serde_json::from_str(&self.body)?)
+    }
+
+    /// Wraps data parsed from this response with its cache state
+    pub fn with<T>(&self, data: T) -> Cached<T> {
+        Cached {
+            data,
+            stale: self.stale,
+            fetched_at: self.fetched_at,
+        }
+    }
+}
+
+/// Cache file for a request. Every credential header is part of the key so
+/// responses fetched with one token are never served to another.
+fn cache_path(request: &reqwest::Request) -> PathBuf {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    // Entries keyed before GitLab's header counted could hold private data under an anonymous key
+    hasher.update(b"v2\n");
+    hasher.update(request.url().as_str().as_bytes());
+    for name in CREDENTIAL_HEADERS {
+        for value in request.headers().get_all(name) {
+            hasher.update(b"\n");
+            hasher.update(name.as_bytes());
+            hasher.update(b":");
+            hasher.update(value.as_bytes());
+        }
+    }
+    let hash = format!("{:x}", hasher.finalize());
+    get_http_cache_dir().join(format!("{}.json", &hash[..32]))
+}
+
+fn header_string(headers: &HeaderMap, name: reqwest::header::HeaderName) -> Option<String> {
+    headers.get(name).and_then(|v| v.to_str().ok()).map(|v| v.to_string())
+}
+
+/// GETs through the response cache. A cached response is revalidated with
+/// `If-None-Match`/`If-Modified-Since`, which costs nothing against GitHub's
+/// rate limit when unchanged. If the server can't be reached, is failing or
+/// is rate limiting us, the cached body is returned marked stale.
+pub async fn get_cached(request: reqwest::RequestBuilder) -> Result<CachedResponse, RepoError> {
+    let (client, request) = request.build_split();
+    let mut request = request?;
+    let path = cache_path(&request);
+    let url = request.url().to_string();
+
+    let cached: Option<CacheEntry> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .filter(|entry: &CacheEntry| entry.url == url);
+    if let Some(entry) = &cached {
+        let headers = request.headers_mut();
+        if let Some(etag) = entry.etag.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+            headers.insert(IF_NONE_MATCH, etag);
+        }
+        if let Some(date) = entry.last_modified.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+            headers.insert(IF_MODIFIED_SINCE, date);
+        }
+    }
+
+    let result = send(reqwest::RequestBuilder::from_parts(client, request)).await;
+    let offline = match &result {
+        Ok(response) => response.status().is_server_error(),
+        Err(e) => e.is_transient(),
+    };
+    if let (true, Some(entry)) = (offline, &cached) {
+        return Ok(CachedResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: entry.body.clone(),
+            stale: true,
+            fetched_at: entry.fetched_at,
+        });
+    }
+
+    let response = result?;
+    if let (StatusCode::NOT_MODIFIED, Some(mut entry)) = (response.status(), cached) {
+        entry.fetched_at = Utc::now();
+        let _ = fs::write(&path, serde_json::to_string(&entry)?);
+        return Ok(CachedResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: entry.body,
+            stale: false,
+            fetched_at: entry.fetched_at,
+        });
+    }
+
+    let status = response.status();
+    let headers = response.headers().clone();
+    let etag = header_string(&headers, ETAG);
+    let last_modified = header_string(&headers, LAST_MODIFIED);
+    let body = response.text().await?;
+    let fetched_at = Utc::now();
+
+    if status.is_success() && fs::create_dir_all(get_http_cache_dir()).is_ok() {
+        let entry = CacheEntry {
+            url,
+            etag,
+            last_modified,
+            fetched_at,
+            body: body.clone(),
+        };
+        let _ = fs::write(&path, serde_json::to_string(&entry)?);
+    }
+
+    Ok(CachedResponse {
+        status,
+        headers,
+        body,
+        stale: false,
+        fetched_at,
+    })
+}
+
+/// Drops cache entries that haven't been used in a long time
+pub fn prune_cache() {
+    let Ok(entries) = fs::read_dir(get_http_cache_dir()) else {
+        return;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let expired = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_none_or(|age| age > CACHE_MAX_AGE);
+        if expired {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
@@ -0,0 +1,477 @@
+//! Gitignore-style rules deciding which files the tree hides. Rules come from
+//! the global patterns in settings, the repo's own `.gitignore` files and
+//! `linguist-vendored` attributes, and the repo's overrides, each layer
+//! overriding the one before it. The `linguist-vendored` and
+//! `linguist-generated` attributes are also kept for classifying what is shown.
+
+use crate::repo::AppSettings;
+
+#[derive(Debug, Clone)]
+struct Rule {
+    /// Segments of the folder the rule was declared in, none for the repo root
+    base: Vec<Vec<char>>,
+    segments: Vec<Segment>,
+    /// Matched against the whole path below `base` rather than just the name
+    anchored: bool,
+    dir_only: bool,
+    negated: bool,
+}
+
+/// One `/`-separated part of a pattern, compiled when the rule is parsed
+#[derive(Debug, Clone)]
+enum Segment {
+    /// `**`: any number of folders, including none
+    AnyFolders,
+    Glob(Vec<Token>),
+}
+
+#[derive(Debug, Clone)]
+enum Token {
+    Literal(char),
+    /// `?`
+    AnyChar,
+    /// `*`
+    AnyRun,
+    /// `[...]`
+    Class { ranges: Vec<(char, char)>, negated: bool },
+}
+
+impl Rule {
+    /// Parses one gitignore line. Blank lines and comments give None.
+    fn parse(base: &str, line: &str) -> Option<Rule> {
+        let mut pattern = line.trim_end();
+        if pattern.is_empty() || pattern.starts_with('#') {
+            return None;
+        }
+
+        // A leading `\` escapes a literal `!` or `#`
+        let negated = pattern.starts_with('!');
+        if negated || pattern.starts_with("\\!") || pattern.starts_with("\\#") {
+            pattern = &pattern[1..];
+        }
+
+        let dir_only = pattern.ends_with('/');
+        pattern = pattern.trim_end_matches('/');
+        let anchored = pattern.contains('/');
+        pattern = pattern.trim_start_matches('/');
+        if pattern.is_empty() {
+            return None;
+        }
+
+        Some(Rule {
+            base: split_path(base.trim_matches('/')),
+            segments: pattern.split('/').map(Segment::compile).collect(),
+            anchored,
+            dir_only,
+            negated,
+        })
+    }
+
+    fn matches(&self, path: &[Vec<char>], is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let relative = match path.split_at_checked(self.base.len()) {
+            Some((base, relative)) if base == self.base.as_slice() && !relative.is_empty() => relative,
+            _ => return false,
+        };
+
+        if self.anchored {
+            glob_match(
+                &self.segments,
+                relative,
+                |segment| matches!(segment, Segment::AnyFolders),
+                |segment, name| segment.matches(name),
+            )
+        } else {
+            relative.last().is_some_and(|name| self.segments[0].matches(name))
+        }
+    }
+}
+
+impl Segment {
+    /// Compiles `*`, `?`, `[...]` and `\` escapes
+    fn compile(text: &str) -> Segment {
+        if text == "**" {
+            return Segment::AnyFolders;
+        }
+
+        let chars: Vec<char> = text.chars().collect();
+        let mut tokens = vec![];
+        let mut i = 0;
+        while i < chars.len() {
+            let (token, len) = match chars[i] {
+                '*' => (Token::AnyRun, 1),
+                '?' => (Token::AnyChar, 1),
+                // An unclosed bracket is literal
+                '[' => char_class(&chars[i + 1..]).map_or((Token::Literal('['), 1), |(class, len)| (class, 1 + len)),
+                '\\' if i + 1 < chars.len() => (Token::Literal(chars[i + 1]), 2),
+                c => (Token::Literal(c), 1),
+            };
+            // Runs of `*` match the same as one
+            if !(matches!(token, Token::AnyRun) && matches!(tokens.last(), Some(Token::AnyRun))) {
+                tokens.push(token);
+            }
+            i += len;
+        }
+        Segment::Glob(tokens)
+    }
+
+    /// Whether one path segment matches. `**` matches any single one.
+    fn matches(&self, name: &[char]) -> bool {
+        match self {
+            Segment::AnyFolders => true,
+            Segment::Glob(tokens) => glob_match(tokens, name, |token| matches!(token, Token::AnyRun), Token::matches),
+        }
+    }
+}
+
+impl Token {
+    fn matches(&self, c: &char) -> bool {
+        match self {
+            Token::Literal(literal) => literal == c,
+            Token::AnyChar => true,
+            Token::AnyRun => false,
+            Token::Class { ranges, negated } => ranges.iter().any(|&(low, high)| low <= *c && *c <= high) != *negated,
+        }
+    }
+}
+
+/// Matches `text` against `pattern`, where star elements stand for any run of
+/// text and every other element for exactly one item. Only the latest star is
+/// ever backtracked to, so a match takes at most |pattern| × |text| steps
+/// however the pattern is built; repo ignore files are untrusted input.
+fn glob_match<P, T>(pattern: &[P], text: &[T], is_star: impl Fn(&P) -> bool, matches: impl Fn(&P, &T) -> bool) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // Pattern position after the latest star, and the text position it resumes from
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && is_star(&pattern[p]) {
+            p += 1;
+            backtrack = Some((p, t));
+        } else if p < pattern.len() && matches(&pattern[p], &text[t]) {
+            p += 1;
+            t += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            // Let the star take one more item and try again from there
+            p = star_p;
+            t = star_t + 1;
+            backtrack = Some((star_p, t));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(is_star)
+}
+
+/// Parses a bracket expression following its `[`, returning it with the
+/// number of chars consumed, including the closing `]`
+fn char_class(pattern: &[char]) -> Option<(Token, usize)> {
+    let negated = matches!(pattern.first(), Some('!') | Some('^'));
+    let mut i = usize::from(negated);
+    let mut ranges = Vec::new();
+
+    // A `]` right at the start is part of the set
+    let start = i;
+    while i < pattern.len() && (pattern[i] != ']' || i == start) {
+        let low = pattern[i];
+        if i + 2 < pattern.len() && pattern[i + 1] == '-' && pattern[i + 2] != ']' {
+            ranges.push((low, pattern[i + 2]));
+            i += 3;
+        } else {
+            ranges.push((low, low));
+            i += 1;
+        }
+    }
+    if i >= pattern.len() {
+        return None;
+    }
+    Some((Token::Class { ranges, negated }, i + 1))
+}
+
+/// Splits a repo-relative path into segments once, for matching against every rule
+fn split_path(path: &str) -> Vec<Vec<char>> {
+    if path.is_empty() {
+        return vec![];
+    }
+    path.split('/').map(|segment| segment.chars().collect()).collect()
+}
+
+/// An ordered list of rules where the last one matching a path decides
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    rules: Vec<Rule>,
+}
+
+impl IgnoreRules {
+    /// Adds gitignore lines applying below `dir`, "" being the repo root
+    pub fn add_gitignore(&mut self, dir: &str, text: &str) {
+        self.rules.extend(text.lines().filter_map(|line| Rule::parse(dir, line)));
+    }
+
+    /// Adds the `.gitattributes` lines of `dir` that set or unset `attribute`,
+    /// so the rules match where it is set
+    pub fn add_attribute(&mut self, dir: &str, text: &str, attribute: &str) {
+        for line in text.lines() {
+            let mut fields = line.split_whitespace();
+            let Some(pattern) = fields.next().filter(|p| !p.starts_with('#')) else {
+                continue;
+            };
+            let mut set = fields.filter_map(|field| {
+                let (negated, field) = match field.strip_prefix(['-', '!']) {
+                    Some(rest) => (true, rest),
+                    None => (false, field),
+                };
+                match field.strip_prefix(attribute)? {
+                    "" | "=true" => Some(!negated),
+                    "=false" if !negated => Some(false),
+                    _ => None,
+                }
+            });
+            // The last mention on the line wins
+            if let Some(set) = set.next_back() {
+                let line = if set { pattern.to_string() } else { format!("!{}", pattern) };
+                self.rules.extend(Rule::parse(dir, &line));
+            }
+        }
+    }
+
+    /// Like `matched`, but a folder's decision also covers everything below it
+    fn matched_within(&self, path: &str, is_dir: bool) -> Option<bool> {
+        let parts = split_path(path);
+        self.matched_parts(&parts, is_dir).or_else(|| {
+            (1..parts.len())
+                .rev()
+                .find_map(|len| self.matched_parts(&parts[..len], true))
+        })
+    }
+
+    /// Some(true) when the deciding rule hides the path, Some(false) when it
+    /// re-includes it, None when no rule matches
+    pub fn matched(&self, path: &str, is_dir: bool) -> Option<bool> {
+        self.matched_parts(&split_path(path), is_dir)
+    }
+
+    fn matched_parts(&self, parts: &[Vec<char>], is_dir: bool) -> Option<bool> {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(parts, is_dir))
+            .map(|rule| !rule.negated)
+    }
+}
+
+/// Every rule layer for one repo
+#[derive(Debug, Clone, Default)]
+pub struct TreeFilter {
+    global: IgnoreRules,
+    repo: IgnoreRules,
+    overrides: IgnoreRules,
+    vendored: IgnoreRules,
+    generated: IgnoreRules,
+    respect_gitignore: bool,
+    hide_vendored: bool,
+}
+
+impl TreeFilter {
+    pub fn new(settings: &AppSettings, overrides: &[String]) -> Self {
+        let mut filter = TreeFilter {
+            respect_gitignore: settings.respect_gitignore,
+            hide_vendored: settings.hide_vendored,
+            ..Default::default()
+        };
+        filter.global.add_gitignore("", &settings.ignore_patterns.join("\n"));
+        filter.overrides.add_gitignore("", &overrides.join("\n"));
+        filter
+    }
+
+    /// Adds the `.gitignore` and `.gitattributes` of the folder at `dir`.
+    /// Parent folders must be added before their children.
+    pub fn add_repo_files(&mut self, dir: &str, gitignore: Option<&str>, gitattributes: Option<&str>) {
+        if let (true, Some(text)) = (self.respect_gitignore, gitignore) {
+            self.repo.add_gitignore(dir, text);
+        }
+        if let Some(text) = gitattributes {
+            self.vendored.add_attribute(dir, text, "linguist-vendored");
+            self.generated.add_attribute(dir, text, "linguist-generated");
+        }
+    }
+
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        if let Some(ignored) = self.overrides.matched(path, is_dir) {
+            return ignored;
+        }
+        if self.hide_vendored && self.vendored(path, is_dir) == Some(true) {
+            return true;
+        }
+        self.repo
+            .matched(path, is_dir)
+            .or_else(|| self.global.matched(path, is_dir))
+            .unwrap_or(false)
+    }
+
+    /// Whether `.gitattributes` marks the path as vendored, None if it doesn't say
+    pub fn vendored(&self, path: &str, is_dir: bool) -> Option<bool> {
+        self.vendored.matched_within(path, is_dir)
+    }
+
+    /// Whether `.gitattributes` marks the path as generated, None if it doesn't say
+    pub fn generated(&self, path: &str, is_dir: bool) -> Option<bool> {
+        self.generated.matched_within(path, is_dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(dir: &str, text: &str) -> IgnoreRules {
+        let mut rules = IgnoreRules::default();
+        rules.add_gitignore(dir, text);
+        rules
+    }
+
+    #[test]
+    fn unanchored_patterns_match_names_at_any_depth() {
+        let rules = rules("", "*.log");
+        assert_eq!(rules.matched("debug.log", false), Some(true));
+        assert_eq!(rules.matched("a/b/debug.log", false), Some(true));
+        assert_eq!(rules.matched("debug.txt", false), None);
+    }
+
+    #[test]
+    fn anchored_patterns_match_from_their_folder() {
+        let rules = rules("", "/build\ndocs/*.md");
+        assert_eq!(rules.matched("build", true), Some(true));
+        assert_eq!(rules.matched("src/build", true), None);
+        assert_eq!(rules.matched("docs/intro.md", false), Some(true));
+        assert_eq!(rules.matched("docs/guide/intro.md", false), None);
+        assert_eq!(rules.matched("src/docs/intro.md", false), None);
+    }
+
+    #[test]
+    fn nested_gitignore_only_applies_below_its_folder() {
+        let rules = rules("web", "dist\n/cache");
+        assert_eq!(rules.matched("web/dist", true), Some(true));
+        assert_eq!(rules.matched("web/app/dist", true), Some(true));
+        assert_eq!(rules.matched("dist", true), None);
+        assert_eq!(rules.matched("web", true), None);
+        assert_eq!(rules.matched("web/cache", true), Some(true));
+        assert_eq!(rules.matched("web/app/cache", true), None);
+    }
+
+    #[test]
+    fn double_star_matches_any_number_of_folders() {
+        let rules = rules("", "**/fixtures\nsrc/**/gen.rs\nout/**");
+        assert_eq!(rules.matched("fixtures", true), Some(true));
+        assert_eq!(rules.matched("a/b/fixtures", true), Some(true));
+        assert_eq!(rules.matched("src/gen.rs", false), Some(true));
+        assert_eq!(rules.matched("src/a/b/gen.rs", false), Some(true));
+        assert_eq!(rules.matched("lib/src/gen.rs", false), None);
+        assert_eq!(rules.matched("out/a/b.txt", false), Some(true));
+    }
+
+    #[test]
+    fn last_matching_rule_decides() {
+        let rules = rules("", "*.txt\n!keep.txt");
+        assert_eq!(rules.matched("drop.txt", false), Some(true));
+        assert_eq!(rules.matched("keep.txt", false), Some(false));
+
+        let rules = self::rules("", "!keep.txt\n*.txt");
+        assert_eq!(rules.matched("keep.txt", false), Some(true));
+    }
+
+    #[test]
+    fn trailing_slash_matches_folders_only() {
+        let rules = rules("", "tmp/");
+        assert_eq!(rules.matched("tmp", true), Some(true));
+        assert_eq!(rules.matched("a/tmp", true), Some(true));
+        assert_eq!(rules.matched("tmp", false), None);
+    }
+
+    #[test]
+    fn comments_blank_lines_and_escapes() {
+        let rules = rules("", "# comment\n\n   \n\\#hash\n\\!bang\nstar\\*\n");
+        assert_eq!(rules.matched("# comment", false), None);
+        assert_eq!(rules.matched("#hash", false), Some(true));
+        assert_eq!(rules.matched("!bang", false), Some(true));
+        assert_eq!(rules.matched("star*", false), Some(true));
+        assert_eq!(rules.matched("stars", false), None);
+    }
+
+    #[test]
+    fn wildcards_and_character_classes() {
+        let rules = rules("", "log?.txt\nfile[0-9].rs\nx[!ab]\n[]]y\nopen[z");
+        assert_eq!(rules.matched("log1.txt", false), Some(true));
+        assert_eq!(rules.matched("log.txt", false), None);
+        assert_eq!(rules.matched("file7.rs", false), Some(true));
+        assert_eq!(rules.matched("filex.rs", false), None);
+        assert_eq!(rules.matched("xc", false), Some(true));
+        assert_eq!(rules.matched("xa", false), None);
+        assert_eq!(rules.matched("]y", false), Some(true));
+        assert_eq!(rules.matched("open[z", false), Some(true));
+    }
+
+    #[test]
+    fn star_does_not_cross_folders() {
+        let rules = rules("", "a/*.rs");
+        assert_eq!(rules.matched("a/lib.rs", false), Some(true));
+        assert_eq!(rules.matched("a/b/lib.rs", false), None);
+    }
+
+    #[test]
+    fn pathological_patterns_finish_quickly() {
+        let rules = rules("", "*a*a*a*a*a*a*a*a*a*a*a*a*b\n**/**/**/**/**/**/**/**/x/**/y");
+        let name = "a".repeat(10_000);
+        assert_eq!(rules.matched(&name, false), None);
+        let path = vec!["x"; 2_000].join("/");
+        assert_eq!(rules.matched(&path, false), None);
+    }
+
+    #[test]
+    fn attributes_set_unset_and_last_mention_wins() {
+        let mut rules = IgnoreRules::default();
+        rules.add_attribute(
+            "",
+            "# vendor/** linguist-vendored\n\
+             vendor/** linguist-vendored\n\
+             vendor/ours/** -linguist-vendored\n\
+             lib/*.js linguist-vendored=true\n\
+             lib/own.js linguist-vendored=false\n\
+             web/** linguist-vendored !linguist-vendored\n\
+             docs/** linguist-generated\n",
+            "linguist-vendored",
+        );
+        assert_eq!(rules.matched("vendor/dep.c", false), Some(true));
+        assert_eq!(rules.matched("vendor/ours/main.c", false), Some(false));
+        assert_eq!(rules.matched("lib/jquery.js", false), Some(true));
+        assert_eq!(rules.matched("lib/own.js", false), Some(false));
+        assert_eq!(rules.matched("web/app.js", false), Some(false));
+        assert_eq!(rules.matched("docs/index.md", false), None);
+    }
+
+    #[test]
+    fn attributes_on_a_folder_cover_its_contents() {
+        let mut filter = TreeFilter::default();
+        filter.add_repo_files("", None, Some("third/ linguist-vendored\nout linguist-generated"));
+        assert_eq!(filter.vendored("third/lib/a.c", false), Some(true));
+        assert_eq!(filter.generated("out/bundle.js", false), Some(true));
+        assert_eq!(filter.vendored("src/a.c", false), None);
+    }
+
+    #[test]
+    fn overrides_win_over_repo_and_global_rules() {
+        let settings = AppSettings {
+            ignore_patterns: vec!["*.snap".to_string()],
+            respect_gitignore: true,
+            ..Default::default()
+        };
+        let mut filter = TreeFilter::new(&settings, &["!keep.snap".to_string(), "secret".to_string()]);
+        filter.add_repo_files("", Some("*.tmp\n!a.snap"), None);
+        assert!(filter.is_ignored("b.snap", false));
+        assert!(!filter.is_ignored("a.snap", false));
+        assert!(!filter.is_ignored("keep.snap", false));
+        assert!(filter.is_ignored("x.tmp", false));
+        assert!(filter.is_ignored("secret", true));
+    }
+}
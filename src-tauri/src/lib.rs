@@ -1,11 +1,31 @@
+mod http;
+mod ignore;
+mod paths;
+mod provider;
+mod queue;
 mod repo;
+mod stats;
+mod tree_store;
 
 use base64::Engine;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::Emitter;
+use provider::{
+    current_commit_sha, download_lfs_object, download_repo_zip, parse_repo_url, resolve_ref, resolve_submodule_url,
+    search_repos as search_repos_impl, submodule_commit, github_token_for, token_for,
+    GitHubEndpoints, ParsedRepoUrl, ProviderKind, RefKind, ResolvedRef,
+};
+use http::Cached;
+use paths::StorageLocations;
+use tree_store::{without_children, TreePage};
+use queue::{batch_status, cancel_batch, create_batch, import_finished, import_started, run_batch, BatchStatus};
 use repo::{
-    build_file_tree, delete_repo as delete_repo_impl, detect_language, download_repo_zip,
-    extract_zip, generate_repo_key, get_default_branch, get_repos_dir, list_repos as list_repos_impl,
-    load_repo_info, load_tree, parse_github_url, read_file_content, save_repo_info, save_tree,
-    search_github_repos as search_repos_impl, fetch_trending_repos as fetch_trending_repos_impl,
+    begin_import, build_file_tree, cancel_import as cancel_import_impl, content_dir, copy_directory, end_import,
+    delete_repo as delete_repo_impl, detect_language,
+    extract_submodule, extract_tar_gz, extract_zip, generate_local_repo_key, generate_repo_key, get_repos_dir,
+    list_repos as list_repos_impl, list_tree_files, load_repo_info, load_tree, load_tree_children, load_tree_root, read_file_content, save_repo_info, save_tree,
+    fetch_trending_repos as fetch_trending_repos_impl,
     load_settings as load_settings_impl, save_settings as save_settings_impl,
     load_favorites as load_favorites_impl, save_favorites as save_favorites_impl,
     export_favorites as export_favorites_impl,
@@ -15,75 +35,558 @@ use repo::{
     create_gist as create_gist_impl,
     get_chat_sessions as get_chat_sessions_impl, get_chat_session as get_chat_session_impl,
     save_chat_session as save_chat_session_impl, delete_chat_session as delete_chat_session_impl,
-    update_repo_last_opened as update_repo_last_opened_impl,
-    FileContent, FileNode, ImportResult, RepoError, RepoInfo, SearchResultItem, AppSettings,
-    TrendingRepo, FavoriteRepo, FileHistoryEntry, CreateGistResult, ChatSession, ChatSessionSummary,
+    update_repo_last_opened as update_repo_last_opened_impl, set_repo_pinned as set_repo_pinned_impl,
+    set_repo_ignore_patterns as set_repo_ignore_patterns_impl,
+    enforce_cache_quota, storage_usage, StorageUsage, imports_running, trees_rebuilding,
+    cleanup_stale_imports, diff_trees, layout_migration_running, lfs_object_path, migrate_repo_keys, migrate_repo_layout, rebuild_trees, lfs_pointer_at, read_file_bytes, parse_gitmodules, read_zip_text, save_submodules, store_blobs, store_zip,
+    StorageMode, SubmoduleInfo, SubmoduleMap, StagedImport, DownloadProgress, ExtractLimits, RefreshResult, RepoUpdateStatus,
+    SourceKind, FileContent, FileNode, ImportResult, RepoError, RepoGroup, RepoInfo,
+    SearchResultItem, AppSettings, TrendingRepo, FavoriteRepo, FileHistoryEntry, CreateGistResult, ChatSession, ChatSessionSummary,
 };
 
 #[tauri::command]
-async fn import_repo_from_github(url: String) -> Result<ImportResult, RepoError> {
-    let parsed = parse_github_url(&url)?;
+async fn import_repo_from_github(app: tauri::AppHandle, url: String) -> Result<ImportResult, RepoError> {
+    import_repo_from_url(app, url, None).await
+}
+
+/// Imports a repo URL, as one item of the batch `batch_id` if given
+async fn import_repo_from_url(app: tauri::AppHandle, url: String, batch_id: Option<&str>) -> Result<ImportResult, RepoError> {
+    let settings = load_settings_impl();
+    let parsed = parse_repo_url(&url, &settings)?;
+    let token = token_for(&parsed, &settings);
+
+    // Resolve the branch, tag or commit (from URL or the default branch)
+    let resolved = resolve_ref(&parsed, token.as_deref()).await?;
+    let repo_key = generate_repo_key(
+        parsed.provider,
+        &parsed.host,
+        &parsed.owner,
+        &parsed.repo,
+        &resolved.name,
+        resolved.subpath.as_deref(),
+    );
+
+    let cancel = begin_import(&repo_key);
+    // Registered with the batch after `begin_import`, so cancelling the batch always reaches it
+    if batch_id.is_some_and(|batch_id| !import_started(batch_id, &repo_key)) {
+        end_import(&repo_key);
+        return Err(RepoError::Cancelled);
+    }
+    let result = import_repo(&app, &url, parsed, resolved, token.as_deref(), &repo_key, &cancel).await;
+    if let Some(batch_id) = batch_id {
+        import_finished(batch_id, &repo_key);
+    }
+    end_import(&repo_key);
+    result
+}
 
-    // Get branch (from URL or API)
-    let branch = match parsed.branch {
-        Some(b) => b,
-        None => get_default_branch(&parsed.owner, &parsed.repo).await?,
+async fn import_repo(
+    app: &tauri::AppHandle,
+    url: &str,
+    parsed: ParsedRepoUrl,
+    resolved: ResolvedRef,
+    token: Option<&str>,
+    repo_key: &str,
+    cancel: &AtomicBool,
+) -> Result<ImportResult, RepoError> {
+    // Build the new copy off to the side; the cached one stays usable until it's done
+    let staged = StagedImport::new(repo_key)?;
+    let zip_path = staged.archive_path();
+
+    // Download ZIP, reporting progress to the frontend
+    download_repo_zip(&parsed, &resolved, token, zip_path, cancel, progress_reporter(app, repo_key, url)).await?;
+
+    // The download may have finished right as the user cancelled
+    if cancel.load(Ordering::SeqCst) {
+        return Err(RepoError::Cancelled);
+    }
+
+    let settings = load_settings_impl();
+    let limits = ExtractLimits::from_settings(&settings);
+    // .gitmodules sits at the repo root, which subfolder imports don't keep
+    let gitmodules = read_zip_text(zip_path, ".gitmodules")?;
+
+    // Either keep the ZIP and index it, or extract it and drop the download
+    match settings.storage_mode {
+        StorageMode::Archive => {
+            store_zip(zip_path, staged.path(), resolved.subpath.as_deref(), &limits)?;
+        }
+        StorageMode::Extracted | StorageMode::Deduplicated => {
+            extract_zip(zip_path, staged.path(), resolved.subpath.as_deref(), &limits)?;
+            std::fs::remove_file(zip_path)?;
+        }
+    }
+
+    if let Some(gitmodules) = gitmodules {
+        import_submodules(app, &parsed, &resolved, &gitmodules, token, &staged, repo_key, cancel, &settings).await?;
+    }
+
+    // Submodule contents go into the blob store along with the rest
+    if settings.storage_mode == StorageMode::Deduplicated {
+        store_blobs(staged.path())?;
+    }
+
+    // Build file tree, rooted at the subfolder for partial imports
+    let tree_name = resolved
+        .subpath
+        .as_deref()
+        .and_then(|p| p.rsplit('/').next())
+        .unwrap_or(&parsed.repo)
+        .to_string();
+    let previous = previous_info(repo_key);
+    let ignore_patterns = previous.as_ref().map(|info| info.ignore_patterns.clone()).unwrap_or_default();
+    let tree = build_file_tree(staged.path(), &tree_name, &settings, &ignore_patterns)?;
+
+    // Create repo info
+    let now = chrono::Utc::now().to_rfc3339();
+    let info = RepoInfo {
+        key: repo_key.to_string(),
+        owner: parsed.owner,
+        repo: parsed.repo,
+        source: SourceKind::Remote,
+        provider: parsed.provider,
+        host: parsed.host,
+        branch: resolved.name,
+        ref_kind: resolved.kind,
+        commit_sha: resolved.commit_sha,
+        subpath: resolved.subpath,
+        imported_at: now.clone(),
+        url: url.to_string(),
+        last_opened_at: Some(now),
+        pinned: previous.is_some_and(|info| info.pinned),
+        ignore_patterns,
     };
 
-    let repo_key = generate_repo_key(&parsed.owner, &parsed.repo);
-    let repos_dir = get_repos_dir();
-    let repo_dir = repos_dir.join(&repo_key);
-    let zip_path = repos_dir.join(format!("{}.zip", repo_key));
+    // Save metadata, then swap the finished copy into place
+    save_repo_info(staged.path(), &info)?;
+    save_tree(staged.path(), &tree)?;
+    staged.commit()?;
 
-    // Download ZIP
-    download_repo_zip(&parsed.owner, &parsed.repo, &branch, &zip_path).await?;
+    // Eviction failing shouldn't fail the import that triggered it
+    let _ = enforce_cache_quota(&settings, Some(repo_key));
 
-    // Extract ZIP
-    extract_zip(&zip_path, &repo_dir)?;
+    Ok(ImportResult {
+        repo_key: repo_key.to_string(),
+        info,
+        tree: without_children(&tree),
+    })
+}
 
-    // Build file tree
-    let tree = build_file_tree(&repo_dir, &parsed.repo)?;
+/// Runs disk-heavy work on the blocking pool instead of the thread that called the command
+async fn run_blocking<T: Send + 'static>(
+    work: impl FnOnce() -> Result<T, RepoError> + Send + 'static,
+) -> Result<T, RepoError> {
+    tauri::async_runtime::spawn_blocking(work)
+        .await
+        .map_err(|e| RepoError::IoError(std::io::Error::other(e.to_string())))?
+}
+
+/// The info of the copy being replaced, so re-importing a repo keeps its pin
+/// and ignore overrides
+fn previous_info(repo_key: &str) -> Option<RepoInfo> {
+    load_repo_info(&get_repos_dir().join(repo_key)).ok()
+}
+
+fn progress_reporter<'a>(
+    app: &'a tauri::AppHandle,
+    repo_key: &'a str,
+    url: &'a str,
+) -> impl FnMut(u64, Option<u64>) + 'a {
+    move |received, total| {
+        let _ = app.emit(
+            "import-progress",
+            DownloadProgress {
+                repo_key: repo_key.to_string(),
+                url: url.to_string(),
+                received_bytes: received,
+                total_bytes: total,
+            },
+        );
+    }
+}
+
+/// Records each submodule from `.gitmodules` with the commit the parent pins and,
+/// when enabled in settings, downloads that commit into the submodule's folder.
+/// Nested submodules aren't followed. A submodule that can't be resolved or
+/// fetched is left as an empty folder instead of failing the whole import.
+#[allow(clippy::too_many_arguments)]
+async fn import_submodules(
+    app: &tauri::AppHandle,
+    parsed: &ParsedRepoUrl,
+    resolved: &ResolvedRef,
+    gitmodules: &str,
+    token: Option<&str>,
+    staged: &StagedImport,
+    repo_key: &str,
+    cancel: &AtomicBool,
+    settings: &AppSettings,
+) -> Result<(), RepoError> {
+    let git_ref = resolved.commit_sha.as_deref().unwrap_or(&resolved.name);
+    let limits = ExtractLimits::from_settings(settings);
+    let mut submodules = SubmoduleMap::new();
+
+    for submodule in parse_gitmodules(gitmodules) {
+        // Subfolder imports only keep the submodules inside that folder
+        let relative_path = match resolved.subpath.as_deref() {
+            Some(prefix) => match submodule.path.strip_prefix(prefix).and_then(|rest| rest.strip_prefix('/')) {
+                Some(rest) => rest.to_string(),
+                None => continue,
+            },
+            None => submodule.path.clone(),
+        };
+
+        let url = resolve_submodule_url(parsed, &submodule.url);
+        let commit_sha = submodule_commit(parsed, git_ref, &submodule.path, token)
+            .await
+            .unwrap_or(None);
+
+        // Archive-backed repos have no folder to unpack into; the tree shows the submodule unpopulated
+        let archive_backed = settings.storage_mode == StorageMode::Archive;
+        if !archive_backed {
+            std::fs::create_dir_all(content_dir(staged.path()).join(&relative_path))?;
+        }
+
+        let mut populated = false;
+        if let (true, false, Some(sha)) = (settings.fetch_submodules, archive_backed, &commit_sha) {
+            match fetch_submodule(app, &url, sha, &relative_path, staged, repo_key, cancel, settings, &limits).await {
+                Ok(()) => populated = true,
+                Err(RepoError::Cancelled) => return Err(RepoError::Cancelled),
+                Err(_) => {}
+            }
+        }
+
+        submodules.insert(
+            relative_path,
+            SubmoduleInfo {
+                url,
+                commit_sha,
+                populated,
+            },
+        );
+    }
+
+    save_submodules(staged.path(), &submodules)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn fetch_submodule(
+    app: &tauri::AppHandle,
+    url: &str,
+    sha: &str,
+    submodule_path: &str,
+    staged: &StagedImport,
+    repo_key: &str,
+    cancel: &AtomicBool,
+    settings: &AppSettings,
+    limits: &ExtractLimits,
+) -> Result<(), RepoError> {
+    let parsed = parse_repo_url(url, settings)?;
+    let token = token_for(&parsed, settings);
+    let resolved = ResolvedRef {
+        kind: RefKind::Commit,
+        name: sha.to_string(),
+        commit_sha: Some(sha.to_string()),
+        subpath: None,
+    };
+
+    // The parent's archive is gone by now, so its slot is reused for each submodule
+    let zip_path = staged.archive_path();
+    download_repo_zip(&parsed, &resolved, token.as_deref(), zip_path, cancel, progress_reporter(app, repo_key, url))
+        .await?;
+    let result = extract_submodule(zip_path, staged.path(), submodule_path, limits);
+    let _ = std::fs::remove_file(zip_path);
+    result
+}
+
+/// Builds the tree and metadata for a staged repo filled from a local source,
+/// then moves it into place
+fn finish_local_import(
+    staged: StagedImport,
+    repo_key: String,
+    name: String,
+    source: SourceKind,
+    source_path: &Path,
+) -> Result<ImportResult, RepoError> {
+    let settings = load_settings_impl();
+    if settings.storage_mode == StorageMode::Deduplicated {
+        store_blobs(staged.path())?;
+    }
+    let previous = previous_info(&repo_key);
+    let ignore_patterns = previous.as_ref().map(|info| info.ignore_patterns.clone()).unwrap_or_default();
+    let tree = build_file_tree(staged.path(), &name, &settings, &ignore_patterns)?;
 
-    // Create repo info
     let now = chrono::Utc::now().to_rfc3339();
     let info = RepoInfo {
         key: repo_key.clone(),
-        owner: parsed.owner,
-        repo: parsed.repo,
-        branch,
+        owner: "local".to_string(),
+        repo: name,
+        source,
+        provider: ProviderKind::default(),
+        host: String::new(),
+        branch: String::new(),
+        ref_kind: RefKind::default(),
+        commit_sha: None,
+        subpath: None,
         imported_at: now.clone(),
-        url,
+        url: source_path.to_string_lossy().to_string(),
         last_opened_at: Some(now),
+        pinned: previous.is_some_and(|info| info.pinned),
+        ignore_patterns,
     };
 
-    // Save metadata
-    save_repo_info(&repo_dir, &info)?;
-    save_tree(&repo_dir, &tree)?;
+    save_repo_info(staged.path(), &info)?;
+    save_tree(staged.path(), &tree)?;
+    staged.commit()?;
+    let _ = enforce_cache_quota(&settings, Some(&repo_key));
 
     Ok(ImportResult {
         repo_key,
         info,
-        tree,
+        tree: without_children(&tree),
+    })
+}
+
+#[tauri::command]
+async fn import_local_archive(path: String) -> Result<ImportResult, RepoError> {
+    let archive_path = PathBuf::from(&path);
+    let file_name = archive_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let lower = file_name.to_ascii_lowercase();
+
+    let name = [".tar.gz", ".tgz", ".zip"]
+        .iter()
+        .find_map(|ext| lower.strip_suffix(ext).map(|stem| file_name[..stem.len()].to_string()))
+        .ok_or_else(|| RepoError::InvalidUrl(format!("Unsupported archive: {}", file_name)))?;
+
+    let repo_key = generate_local_repo_key(&archive_path);
+    run_local_import(repo_key.clone(), move || {
+        let staged = StagedImport::new(&repo_key)?;
+
+        let limits = ExtractLimits::from_settings(&load_settings_impl());
+        if lower.ends_with(".zip") {
+            extract_zip(&archive_path, staged.path(), None, &limits)?;
+        } else {
+            extract_tar_gz(&archive_path, staged.path(), &limits)?;
+        }
+
+        finish_local_import(staged, repo_key, name, SourceKind::LocalArchive, &archive_path)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn import_local_directory(path: String) -> Result<ImportResult, RepoError> {
+    let source_dir = PathBuf::from(&path);
+    if !source_dir.is_dir() {
+        return Err(RepoError::RepoNotFound(path));
+    }
+
+    let name = source_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "local".to_string());
+
+    let repo_key = generate_local_repo_key(&source_dir);
+    run_local_import(repo_key.clone(), move || {
+        let staged = StagedImport::new(&repo_key)?;
+
+        let limits = ExtractLimits::from_settings(&load_settings_impl());
+        copy_directory(&source_dir, staged.path(), &limits)?;
+
+        finish_local_import(staged, repo_key, name, SourceKind::LocalDirectory, &source_dir)
+    })
+    .await
+}
+
+/// Runs a local import off the async thread, registered as running like any
+/// other import. Local imports can't be cancelled.
+async fn run_local_import(
+    repo_key: String,
+    work: impl FnOnce() -> Result<ImportResult, RepoError> + Send + 'static,
+) -> Result<ImportResult, RepoError> {
+    begin_import(&repo_key);
+    let result = run_blocking(work).await;
+    end_import(&repo_key);
+    result
+}
+
+/// Asks the provider whether the branch or tag a repo was imported from has moved.
+/// Local sources and pinned commits never report updates.
+#[tauri::command]
+async fn check_repo_updates(repo_key: String) -> Result<RepoUpdateStatus, RepoError> {
+    let info = load_repo_info(&get_repos_dir().join(&repo_key))?;
+
+    let latest_sha = if info.source == SourceKind::Remote && info.ref_kind != RefKind::Commit {
+        let settings = load_settings_impl();
+        let parsed = ParsedRepoUrl::from_repo_info(&info, &settings);
+        let token = token_for(&parsed, &settings);
+        current_commit_sha(&parsed, info.ref_kind, &info.branch, token.as_deref()).await?
+    } else {
+        info.commit_sha.clone()
+    };
+
+    // Repos imported before SHAs were recorded count as outdated once the head is known
+    let has_update = latest_sha.is_some() && latest_sha != info.commit_sha;
+
+    Ok(RepoUpdateStatus {
+        repo_key,
+        current_sha: info.commit_sha,
+        latest_sha,
+        has_update,
+    })
+}
+
+/// Re-imports a cached repo from the same source and ref, and reports which files changed
+#[tauri::command]
+async fn refresh_repo(app: tauri::AppHandle, repo_key: String) -> Result<RefreshResult, RepoError> {
+    let repo_dir = get_repos_dir().join(&repo_key);
+    let info = load_repo_info(&repo_dir)?;
+    let old_tree = load_tree(&repo_dir)?;
+
+    let import = match info.source {
+        SourceKind::Remote => {
+            let settings = load_settings_impl();
+            let parsed = ParsedRepoUrl::from_repo_info(&info, &settings);
+            let token = token_for(&parsed, &settings);
+            let commit_sha = match info.ref_kind {
+                RefKind::Commit => info.commit_sha.clone(),
+                kind => current_commit_sha(&parsed, kind, &info.branch, token.as_deref()).await?,
+            };
+            let resolved = ResolvedRef {
+                kind: info.ref_kind,
+                name: info.branch.clone(),
+                commit_sha,
+                subpath: info.subpath.clone(),
+            };
+
+            let cancel = begin_import(&repo_key);
+            let result = import_repo(&app, &info.url, parsed, resolved, token.as_deref(), &repo_key, &cancel).await;
+            end_import(&repo_key);
+            result?
+        }
+        SourceKind::LocalDirectory => import_local_directory(info.url.clone()).await?,
+        SourceKind::LocalArchive => import_local_archive(info.url.clone()).await?,
+    };
+
+    let changes = diff_trees(&old_tree, &load_tree(&repo_dir)?);
+
+    Ok(RefreshResult {
+        import,
+        previous_sha: info.commit_sha,
+        changes,
     })
 }
 
+/// Queues `urls` for import in the background and returns the batch right away.
+/// Progress is reported through `batch-import-progress` events.
+fn spawn_batch_import(app: tauri::AppHandle, urls: Vec<String>) -> BatchStatus {
+    let status = create_batch(urls);
+    let concurrency = load_settings_impl().max_concurrent_imports;
+    let batch_id = status.batch_id.clone();
+
+    let import_app = app.clone();
+    let import_batch_id = batch_id.clone();
+    tauri::async_runtime::spawn(run_batch(
+        batch_id,
+        concurrency,
+        move |url| {
+            let app = import_app.clone();
+            let batch_id = import_batch_id.clone();
+            async move {
+                import_repo_from_url(app, url, Some(&batch_id))
+                    .await
+                    .map(|result| result.repo_key)
+            }
+        },
+        move |status| {
+            let _ = app.emit("batch-import-progress", status);
+        },
+    ));
+
+    status
+}
+
+#[tauri::command]
+fn start_batch_import(app: tauri::AppHandle, urls: Vec<String>) -> BatchStatus {
+    spawn_batch_import(app, urls)
+}
+
+#[tauri::command]
+fn import_all_favorites(app: tauri::AppHandle) -> BatchStatus {
+    let urls = load_favorites_impl().into_iter().map(|f| f.url).collect();
+    spawn_batch_import(app, urls)
+}
+
+#[tauri::command]
+fn get_batch_import_status(batch_id: String) -> Option<BatchStatus> {
+    batch_status(&batch_id)
+}
+
+#[tauri::command]
+fn cancel_batch_import(batch_id: String) -> bool {
+    cancel_batch(&batch_id)
+}
+
+#[tauri::command]
+fn cancel_import(repo_key: String) -> bool {
+    cancel_import_impl(&repo_key)
+}
+
 #[tauri::command]
 async fn read_text_file(repo_key: String, file_path: String) -> Result<FileContent, RepoError> {
     let repo_dir = get_repos_dir().join(&repo_key);
-    let full_path = repo_dir.join(&file_path);
 
-    read_file_content(&full_path)
+    read_file_content(&repo_dir, &file_path)
+}
+
+/// Downloads the real content of a Git LFS pointer file, authenticated with the
+/// configured token, and returns the file as it now reads
+#[tauri::command]
+async fn fetch_lfs_object(repo_key: String, file_path: String) -> Result<FileContent, RepoError> {
+    let repo_dir = get_repos_dir().join(&repo_key);
+    let info = load_repo_info(&repo_dir)?;
+    let pointer = lfs_pointer_at(&repo_dir, &file_path)
+        .ok_or_else(|| RepoError::LfsError(format!("{} is not an LFS pointer", file_path)))?;
+    if info.source != SourceKind::Remote {
+        return Err(RepoError::LfsError("local imports have no LFS server".into()));
+    }
+
+    let object_path = lfs_object_path(&repo_dir, &pointer.oid);
+    if !object_path.exists() {
+        let settings = load_settings_impl();
+        let parsed = ParsedRepoUrl::from_repo_info(&info, &settings);
+        let token = token_for(&parsed, &settings);
+        download_lfs_object(&parsed, &pointer, token.as_deref(), &object_path).await?;
+    }
+
+    read_file_content(&repo_dir, &file_path)
 }
 
 #[tauri::command]
-async fn list_recent_repos() -> Result<Vec<RepoInfo>, RepoError> {
+async fn list_recent_repos() -> Result<Vec<RepoGroup>, RepoError> {
     list_repos_impl()
 }
 
+/// The root of a repo's tree; its folders are listed with `get_tree_children`
 #[tauri::command]
 async fn get_repo_tree(repo_key: String) -> Result<FileNode, RepoError> {
     let repo_dir = get_repos_dir().join(&repo_key);
-    load_tree(&repo_dir)
+    load_tree_root(&repo_dir)
+}
+
+/// One page of the children of the folder at `path`, "" being the root
+#[tauri::command]
+async fn get_tree_children(repo_key: String, path: String, offset: usize, limit: usize) -> Result<TreePage, RepoError> {
+    let repo_dir = get_repos_dir().join(&repo_key);
+    load_tree_children(&repo_dir, &path, offset, limit)
+}
+
+#[tauri::command]
+async fn list_repo_files(repo_key: String) -> Result<Vec<String>, RepoError> {
+    let repo_dir = get_repos_dir().join(&repo_key);
+    list_tree_files(&repo_dir)
 }
 
 #[tauri::command]
@@ -104,14 +607,17 @@ fn get_file_language(file_path: String) -> String {
 
 #[tauri::command]
 fn get_repo_path(repo_key: String) -> String {
-    get_repos_dir().join(&repo_key).to_string_lossy().to_string()
+    // Indexed repos have no content folder, so they open at the repo dir
+    let repo_dir = get_repos_dir().join(&repo_key);
+    let files = content_dir(&repo_dir);
+    let path = if files.exists() { files } else { repo_dir };
+    path.to_string_lossy().to_string()
 }
 
 #[tauri::command]
 async fn read_binary_file_data_url(repo_key: String, file_path: String) -> Result<String, RepoError> {
     let repo_dir = get_repos_dir().join(&repo_key);
-    let full_path = repo_dir.join(&file_path);
-    let bytes = std::fs::read(&full_path)?;
+    let bytes = read_file_bytes(&repo_dir, &file_path)?;
     let ext = std::path::Path::new(&file_path)
         .extension()
         .and_then(|e| e.to_str())
@@ -135,8 +641,24 @@ async fn read_binary_file_data_url(repo_key: String, file_path: String) -> Resul
 }
 
 #[tauri::command]
-async fn search_github_repos(query: String, token: Option<String>) -> Result<Vec<SearchResultItem>, RepoError> {
-    search_repos_impl(&query, token.as_deref()).await
+async fn search_github_repos(query: String, token: Option<String>) -> Result<Cached<Vec<SearchResultItem>>, RepoError> {
+    search_repos_impl(ProviderKind::GitHub, None, &query, token.as_deref(), &load_settings_impl()).await
+}
+
+#[tauri::command]
+async fn search_repos(
+    provider: ProviderKind,
+    host: Option<String>,
+    query: String,
+    token: Option<String>,
+) -> Result<Cached<Vec<SearchResultItem>>, RepoError> {
+    let settings = load_settings_impl();
+    // Configured GitHub instances bring their own token
+    let token = token.or_else(|| match (provider, host.as_deref()) {
+        (ProviderKind::GitHub, Some(host)) => github_token_for(host, &settings),
+        _ => None,
+    });
+    search_repos_impl(provider, host.as_deref(), &query, token.as_deref(), &settings).await
 }
 
 #[tauri::command]
@@ -144,8 +666,9 @@ async fn get_trending_repos(
     language: Option<String>,
     since: String,
     spoken_language: Option<String>,
-) -> Result<Vec<TrendingRepo>, RepoError> {
-    fetch_trending_repos_impl(language.as_deref(), &since, spoken_language.as_deref()).await
+) -> Result<Cached<Vec<TrendingRepo>>, RepoError> {
+    let endpoints = GitHubEndpoints::for_host(ProviderKind::GitHub.default_host(), &load_settings_impl());
+    fetch_trending_repos_impl(&endpoints.web, language.as_deref(), &since, spoken_language.as_deref()).await
 }
 
 #[tauri::command]
@@ -154,8 +677,25 @@ fn get_settings() -> AppSettings {
 }
 
 #[tauri::command]
-fn update_settings(settings: AppSettings) -> Result<(), RepoError> {
-    save_settings_impl(&settings)
+async fn update_settings(settings: AppSettings) -> Result<(), RepoError> {
+    // Reject a bad proxy before it's saved
+    http::configure(&settings)?;
+    let previous = load_settings_impl();
+    save_settings_impl(&settings)?;
+
+    run_blocking(move || {
+        // A lowered quota applies right away
+        let _ = enforce_cache_quota(&settings, None);
+        // So do changed ignore rules
+        if settings.ignore_patterns != previous.ignore_patterns
+            || settings.respect_gitignore != previous.respect_gitignore
+            || settings.hide_vendored != previous.hide_vendored
+        {
+            rebuild_trees(&settings);
+        }
+        Ok(())
+    })
+    .await
 }
 
 #[tauri::command]
@@ -222,8 +762,14 @@ async fn create_gist(
     content: String,
     description: String,
     public: bool,
+    host: Option<String>,
 ) -> Result<CreateGistResult, RepoError> {
-    create_gist_impl(&token, &filename, &content, &description, public).await
+    // Gists go to the instance the snippet came from; github.com when not given
+    let settings = load_settings_impl();
+    let host = host.as_deref().unwrap_or(ProviderKind::GitHub.default_host());
+    let endpoints = GitHubEndpoints::for_host(host, &settings);
+    let token = github_token_for(host, &settings).unwrap_or(token);
+    create_gist_impl(&endpoints.api, &token, &filename, &content, &description, public).await
 }
 
 #[tauri::command]
@@ -251,20 +797,107 @@ fn update_repo_last_opened(repo_key: String) -> Result<(), RepoError> {
     update_repo_last_opened_impl(&repo_key)
 }
 
+#[tauri::command]
+fn set_repo_pinned(repo_key: String, pinned: bool) -> Result<(), RepoError> {
+    set_repo_pinned_impl(&repo_key, pinned)
+}
+
+#[tauri::command]
+async fn set_repo_ignore_patterns(repo_key: String, patterns: Vec<String>) -> Result<FileNode, RepoError> {
+    run_blocking(move || set_repo_ignore_patterns_impl(&repo_key, patterns, &load_settings_impl())).await
+}
+
+#[tauri::command]
+fn get_storage_locations() -> StorageLocations {
+    paths::locations()
+}
+
+/// Moves all app data and config to new folders, or next to the executable
+/// when `portable` is set. Unset folders mean the platform defaults.
+#[tauri::command]
+async fn move_storage(
+    data_dir: Option<String>,
+    config_dir: Option<String>,
+    portable: bool,
+) -> Result<StorageLocations, RepoError> {
+    if let Some(job) = storage_in_use() {
+        return Err(RepoError::ImportRunning(job.into()));
+    }
+    // Copies and checks every file under the roots
+    run_blocking(move || {
+        paths::move_storage(data_dir.map(PathBuf::from), config_dir.map(PathBuf::from), portable)
+    })
+    .await
+}
+
+/// The background work still reading or writing under the data root, if any
+fn storage_in_use() -> Option<&'static str> {
+    if layout_migration_running() {
+        Some("The storage layout migration")
+    } else if imports_running() {
+        Some("An import")
+    } else if trees_rebuilding() {
+        Some("A tree rebuild")
+    } else {
+        None
+    }
+}
+
+/// Whether cached repos are still being moved to the current storage layout
+#[tauri::command]
+fn is_layout_migration_running() -> bool {
+    layout_migration_running()
+}
+
+/// Disk usage of every cached repo and of the cache as a whole
+#[tauri::command]
+async fn get_storage_usage() -> Result<StorageUsage, RepoError> {
+    storage_usage(&load_settings_impl())
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
+        .setup(|app| {
+            // Runs before anything else looks for repos; retried next launch on failure
+            let _ = paths::migrate_legacy_repos();
+            // Leaves the version marker unwritten on failure, so it's retried next launch
+            let _ = migrate_repo_keys();
+            cleanup_stale_imports();
+            http::prune_cache();
+
+            // Rehashes every cached file, so it runs off the main thread while the UI says so
+            let app = app.handle().clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                let _ = migrate_repo_layout(&load_settings_impl());
+                let _ = app.emit("layout-migration-finished", ());
+            });
+            Ok(())
+        })
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .invoke_handler(tauri::generate_handler![
             import_repo_from_github,
+            cancel_import,
+            start_batch_import,
+            import_all_favorites,
+            get_batch_import_status,
+            cancel_batch_import,
+            check_repo_updates,
+            refresh_repo,
+            import_local_archive,
+            import_local_directory,
             read_text_file,
+            fetch_lfs_object,
             list_recent_repos,
             get_repo_tree,
+            get_tree_children,
+            list_repo_files,
             get_repo_info,
             delete_repo,
             get_file_language,
             search_github_repos,
+            search_repos,
             get_trending_repos,
             get_settings,
             update_settings,
@@ -285,6 +918,12 @@ pub fn run() {
             save_chat_session,
             delete_chat_session,
             update_repo_last_opened,
+            set_repo_pinned,
+            set_repo_ignore_patterns,
+            get_storage_usage,
+            is_layout_migration_running,
+            get_storage_locations,
+            move_storage,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,262 @@
+//! Where RepoRead keeps its files. Data (repos, screenshots, the HTTP cache)
+//! and config (settings and the JSON stores) each live under a root that
+//! defaults to the platform's app folders, can be pointed elsewhere, or sits
+//! next to the executable in portable mode. `move_storage` relocates existing
+//! files when the roots change.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+
+use crate::repo::RepoError;
+
+/// A file of this name next to the executable turns on portable mode
+const PORTABLE_MARKER: &str = "portable";
+/// Custom roots are recorded in the default config folder, since a custom
+/// config folder can't hold its own location
+const LOCATIONS_FILE: &str = "locations.json";
+
+/// What the app keeps under each root; anything else there is left alone
+const DATA_ENTRIES: [&str; 3] = ["repos", "screenshots", "http-cache"];
+const CONFIG_ENTRIES: [&str; 4] = ["settings.json", "favorites.json", "file_history.json", "chat_history.json"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct CustomRoots {
+    data_dir: Option<PathBuf>,
+    config_dir: Option<PathBuf>,
+}
+
+/// The roots in use
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageLocations {
+    pub data_dir: PathBuf,
+    pub config_dir: PathBuf,
+    pub portable: bool,
+}
+
+fn default_data_dir() -> PathBuf {
+    directories::ProjectDirs::from("com", "xnu", "RepoRead")
+        .map(|dirs| dirs.data_dir().to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("./data"))
+}
+
+/// Where repos went on platforms without app folders, before data moved under one root
+const LEGACY_FALLBACK_REPOS: &str = "./repos/repos";
+
+/// Moves repos cached at the old fallback location into the data root. Only
+/// platforms without app folders ever used it, and nothing is moved if the
+/// data root already has repos.
+pub fn migrate_legacy_repos() -> Result<(), RepoError> {
+    if directories::ProjectDirs::from("com", "xnu", "RepoRead").is_some() {
+        return Ok(());
+    }
+    let legacy = PathBuf::from(LEGACY_FALLBACK_REPOS);
+    let repos = data_dir().join("repos");
+    if !legacy.is_dir() || repos.exists() {
+        return Ok(());
+    }
+
+    fs::create_dir_all(data_dir())?;
+    if fs::rename(&legacy, &repos).is_err() {
+        // The data root may be on another volume
+        copy_verified(&legacy, &repos)?;
+        remove_path(&legacy);
+    }
+    // Only succeeds once nothing else is left there
+    if let Some(parent) = legacy.parent() {
+        let _ = fs::remove_dir(parent);
+    }
+    Ok(())
+}
+
+fn default_config_dir() -> PathBuf {
+    directories::ProjectDirs::from("com", "xnu", "RepoRead")
+        .map(|dirs| dirs.config_dir().to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("./config"))
+}
+
+fn executable_dir() -> Result<PathBuf, RepoError> {
+    std::env::current_exe()?
+        .parent()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| RepoError::StorageMove("executable has no parent folder".into()))
+}
+
+fn portable_locations(dir: &Path) -> StorageLocations {
+    StorageLocations {
+        data_dir: dir.join("data"),
+        config_dir: dir.join("config"),
+        portable: true,
+    }
+}
+
+fn locations_path() -> PathBuf {
+    default_config_dir().join(LOCATIONS_FILE)
+}
+
+/// Portable mode wins over custom roots, which win over the defaults
+fn resolve() -> StorageLocations {
+    if let Ok(dir) = executable_dir() {
+        if dir.join(PORTABLE_MARKER).exists() {
+            return portable_locations(&dir);
+        }
+    }
+
+    let custom: CustomRoots = fs::read_to_string(locations_path())
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    StorageLocations {
+        data_dir: custom.data_dir.unwrap_or_else(default_data_dir),
+        config_dir: custom.config_dir.unwrap_or_else(default_config_dir),
+        portable: false,
+    }
+}
+
+fn current() -> &'static RwLock<StorageLocations> {
+    static CURRENT: OnceLock<RwLock<StorageLocations>> = OnceLock::new();
+    CURRENT.get_or_init(|| RwLock::new(resolve()))
+}
+
+pub fn locations() -> StorageLocations {
+    current().read().unwrap().clone()
+}
+
+pub fn data_dir() -> PathBuf {
+    current().read().unwrap().data_dir.clone()
+}
+
+pub fn config_dir() -> PathBuf {
+    current().read().unwrap().config_dir.clone()
+}
+
+fn hash_file(path: &Path) -> io::Result<String> {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Copies a file or folder tree, checking each copied file against its source
+fn copy_verified(src: &Path, dest: &Path) -> Result<(), RepoError> {
+    let file_type = fs::symlink_metadata(src)?.file_type();
+    if file_type.is_dir() {
+        fs::create_dir_all(dest)?;
+        for entry in fs::read_dir(src)? {
+            let entry = entry?;
+            copy_verified(&entry.path(), &dest.join(entry.file_name()))?;
+        }
+    } else if file_type.is_file() {
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(src, dest)?;
+        if hash_file(src)? != hash_file(dest)? {
+            return Err(RepoError::StorageMove(format!("{} did not copy intact", src.display())));
+        }
+    }
+    Ok(())
+}
+
+fn remove_path(path: &Path) {
+    let _ = if path.is_dir() { fs::remove_dir_all(path) } else { fs::remove_file(path) };
+}
+
+/// Moves every app file to new roots and switches to them. `portable` puts
+/// them next to the executable; otherwise an unset folder means the platform
+/// default. Files are copied and checked against the originals before the new
+/// roots take effect, and the originals are only removed after that. If
+/// anything fails, the copies are removed and the old roots stay in use.
+/// Don't call this while imports are running.
+pub fn move_storage(
+    data_dir: Option<PathBuf>,
+    config_dir: Option<PathBuf>,
+    portable: bool,
+) -> Result<StorageLocations, RepoError> {
+    let from = locations();
+    let to = if portable {
+        portable_locations(&executable_dir()?)
+    } else {
+        StorageLocations {
+            data_dir: data_dir.clone().unwrap_or_else(default_data_dir),
+            config_dir: config_dir.clone().unwrap_or_else(default_config_dir),
+            portable: false,
+        }
+    };
+    if to == from {
+        return Ok(to);
+    }
+    for dir in [&to.data_dir, &to.config_dir] {
+        if !dir.is_absolute() {
+            return Err(RepoError::StorageMove(format!("{} is not an absolute path", dir.display())));
+        }
+    }
+
+    let moves: Vec<(PathBuf, PathBuf)> = DATA_ENTRIES
+        .iter()
+        .filter(|_| from.data_dir != to.data_dir)
+        .map(|name| (from.data_dir.join(name), to.data_dir.join(name)))
+        .chain(
+            CONFIG_ENTRIES
+                .iter()
+                .filter(|_| from.config_dir != to.config_dir)
+                .map(|name| (from.config_dir.join(name), to.config_dir.join(name))),
+        )
+        .filter(|(src, _)| src.exists())
+        .collect();
+
+    for (src, dest) in &moves {
+        if dest.starts_with(src) || src.starts_with(dest) {
+            return Err(RepoError::StorageMove(format!(
+                "{} and {} overlap",
+                src.display(),
+                dest.display()
+            )));
+        }
+        if dest.exists() {
+            return Err(RepoError::StorageMove(format!("{} already exists", dest.display())));
+        }
+    }
+
+    let mut copied = vec![];
+    let result = (|| -> Result<(), RepoError> {
+        for (src, dest) in &moves {
+            copied.push(dest.clone());
+            copy_verified(src, dest)?;
+        }
+        fs::create_dir_all(&to.data_dir)?;
+        fs::create_dir_all(&to.config_dir)?;
+
+        // Record the new roots where `resolve` will look for them next launch
+        if to.portable {
+            fs::write(executable_dir()?.join(PORTABLE_MARKER), "")?;
+        } else {
+            let custom = CustomRoots {
+                data_dir: data_dir.filter(|dir| *dir != default_data_dir()),
+                config_dir: config_dir.filter(|dir| *dir != default_config_dir()),
+            };
+            if let Some(parent) = locations_path().parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(locations_path(), serde_json::to_string_pretty(&custom)?)?;
+            if from.portable {
+                fs::remove_file(executable_dir()?.join(PORTABLE_MARKER))?;
+            }
+        }
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        copied.iter().for_each(|dest| remove_path(dest));
+        return Err(e);
+    }
+
+    *current().write().unwrap() = to.clone();
+    for (src, _) in &moves {
+        remove_path(src);
+    }
+    Ok(to)
+}
@@ -0,0 +1,169 @@
+use serde::Deserialize;
+
+use super::{RefKind, RepoApi, RepoPath, ResolvedRef};
+use crate::http;
+use crate::repo::RepoError;
+
+const API_BASE: &str = "https://api.bitbucket.org/2.0";
+
+#[derive(Debug, Deserialize)]
+struct BitbucketBranchName {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct BitbucketRepoResponse {
+    mainbranch: Option<BitbucketBranchName>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BitbucketTarget {
+    hash: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct BitbucketRefResponse {
+    target: BitbucketTarget,
+}
+
+#[derive(Debug, Deserialize)]
+struct BitbucketNamedRef {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct BitbucketRefList {
+    values: Vec<BitbucketNamedRef>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BitbucketCommitResponse {
+    hash: String,
+}
+
+pub(super) fn authorize(request: reqwest::RequestBuilder, token: Option<&str>) -> reqwest::RequestBuilder {
+    match token {
+        Some(t) if !t.is_empty() => request.header("Authorization", format!("Bearer {}", t)),
+        _ => request,
+    }
+}
+
+fn api_get(client: &reqwest::Client, url: &str, token: Option<&str>) -> reqwest::RequestBuilder {
+    let request = client
+        .get(url)
+        .header("Accept", "application/json");
+    authorize(request, token)
+}
+
+/// `workspace/repo[/src/<ref> | /branch/<b> | /commits/<sha>]`
+pub(super) fn parse_path(parts: &[&str]) -> Result<RepoPath, RepoError> {
+    if parts.len() < 2 {
+        return Err(RepoError::InvalidUrl("Missing workspace or repo".into()));
+    }
+
+    let view = &parts[2..];
+    let (git_ref, ref_kind) = match view {
+        ["src", rest @ ..] if !rest.is_empty() => (Some(rest.join("/")), None),
+        ["branch", rest @ ..] if !rest.is_empty() => {
+            (Some(rest.join("/")), Some(RefKind::Branch))
+        }
+        ["commits", sha, ..] => (Some(sha.to_string()), Some(RefKind::Commit)),
+        _ => (None, None),
+    };
+
+    Ok(RepoPath {
+        owner: parts[0].to_string(),
+        repo: parts[1].to_string(),
+        git_ref,
+        ref_kind,
+    })
+}
+
+pub(super) async fn default_branch(api: &RepoApi<'_>) -> Result<Option<String>, RepoError> {
+    let url = format!("{}/repositories/{}/{}", API_BASE, api.owner, api.repo);
+    let response = http::get_cached(api_get(api.client, &url, api.token)).await?;
+
+    super::ensure_authorized(response.status, &response.headers, api.token)?;
+    if !response.status.is_success() {
+        return Ok(None);
+    }
+
+    let repo_info: BitbucketRepoResponse = response.json()?;
+    Ok(repo_info.mainbranch.map(|b| b.name))
+}
+
+pub(super) async fn lookup_ref(
+    api: &RepoApi<'_>,
+    kind: RefKind,
+    name: &str,
+) -> Result<Option<String>, RepoError> {
+    let collection = match kind {
+        RefKind::Branch => "refs/branches",
+        RefKind::Tag => "refs/tags",
+        RefKind::Commit => "commit",
+    };
+    let url = format!(
+        "{}/repositories/{}/{}/{}/{}",
+        API_BASE,
+        api.owner,
+        api.repo,
+        collection,
+        urlencoding::encode(name)
+    );
+    let response = http::send(api_get(api.client, &url, api.token)).await?;
+
+    super::ensure_authorized(response.status(), response.headers(), api.token)?;
+    if !response.status().is_success() {
+        return Ok(None);
+    }
+
+    let sha = if kind == RefKind::Commit {
+        response.json::<BitbucketCommitResponse>().await?.hash
+    } else {
+        response.json::<BitbucketRefResponse>().await?.target.hash
+    };
+    Ok(Some(sha))
+}
+
+/// Names of branches or tags starting with `prefix`
+pub(super) async fn list_refs(
+    api: &RepoApi<'_>,
+    kind: RefKind,
+    prefix: &str,
+) -> Result<Vec<String>, RepoError> {
+    let collection = if kind == RefKind::Tag { "tags" } else { "branches" };
+    let query = format!("name ~ \"{}\"", prefix);
+    let url = format!(
+        "{}/repositories/{}/{}/refs/{}?q={}&pagelen=100",
+        API_BASE,
+        api.owner,
+        api.repo,
+        collection,
+        urlencoding::encode(&query)
+    );
+    let response = http::send(api_get(api.client, &url, api.token)).await?;
+
+    super::ensure_authorized(response.status(), response.headers(), api.token)?;
+    if !response.status().is_success() {
+        return Ok(vec![]);
+    }
+
+    let refs: BitbucketRefList = response.json().await?;
+    Ok(refs
+        .values
+        .into_iter()
+        .map(|r| r.name)
+        .filter(|name| name.starts_with(prefix))
+        .collect())
+}
+
+pub(super) fn archive_url(api: &RepoApi<'_>, resolved: &ResolvedRef) -> String {
+    let archive_ref = resolved.commit_sha.as_deref().unwrap_or(&resolved.name);
+    format!(
+        "https://{}/{}/{}/get/{}.zip",
+        api.host,
+        api.owner,
+        api.repo,
+        urlencoding::encode(archive_ref)
+    )
+}
@@ -0,0 +1,259 @@
+use serde::Deserialize;
+
+use super::{RefKind, RepoApi, RepoPath, ResolvedRef};
+use crate::http::{self, Cached};
+use crate::repo::{RepoError, SearchResultItem};
+
+#[derive(Debug, Deserialize)]
+struct GiteaRepoResponse {
+    default_branch: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GiteaBranchCommit {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GiteaBranchResponse {
+    commit: GiteaBranchCommit,
+}
+
+#[derive(Debug, Deserialize)]
+struct GiteaTagCommit {
+    sha: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GiteaTagResponse {
+    commit: GiteaTagCommit,
+}
+
+#[derive(Debug, Deserialize)]
+struct GiteaNamedRef {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GiteaCommitResponse {
+    sha: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GiteaContentResponse {
+    #[serde(rename = "type")]
+    content_type: String,
+    sha: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GiteaSearchResponse {
+    data: Vec<GiteaSearchItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GiteaSearchItem {
+    full_name: String,
+    description: Option<String>,
+    stars_count: u64,
+    html_url: String,
+    owner: GiteaOwner,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GiteaOwner {
+    login: String,
+}
+
+pub(super) fn authorize(request: reqwest::RequestBuilder, token: Option<&str>) -> reqwest::RequestBuilder {
+    match token {
+        Some(t) if !t.is_empty() => request.header("Authorization", format!("token {}", t)),
+        _ => request,
+    }
+}
+
+fn api_get(client: &reqwest::Client, url: &str, token: Option<&str>) -> reqwest::RequestBuilder {
+    let request = client
+        .get(url)
+        .header("Accept", "application/json");
+    authorize(request, token)
+}
+
+/// `owner/repo[/src/branch/<b> | /src/tag/<t> | /src/commit/<sha> | /commit/<sha> | /releases/tag/<t>]`
+pub(super) fn parse_path(parts: &[&str]) -> Result<RepoPath, RepoError> {
+    if parts.len() < 2 {
+        return Err(RepoError::InvalidUrl("Missing owner or repo".into()));
+    }
+
+    let view = &parts[2..];
+    let (git_ref, ref_kind) = match view {
+        ["src", "branch", rest @ ..] if !rest.is_empty() => {
+            (Some(rest.join("/")), Some(RefKind::Branch))
+        }
+        ["src", "tag", rest @ ..] if !rest.is_empty() => (Some(rest.join("/")), Some(RefKind::Tag)),
+        ["src", "commit", sha, ..] | ["commit", sha, ..] => {
+            (Some(sha.to_string()), Some(RefKind::Commit))
+        }
+        ["releases", "tag", rest @ ..] if !rest.is_empty() => {
+            (Some(rest.join("/")), Some(RefKind::Tag))
+        }
+        _ => (None, None),
+    };
+
+    Ok(RepoPath {
+        owner: parts[0].to_string(),
+        repo: parts[1].to_string(),
+        git_ref,
+        ref_kind,
+    })
+}
+
+pub(super) async fn default_branch(api: &RepoApi<'_>) -> Result<Option<String>, RepoError> {
+    let url = format!("https://{}/api/v1/repos/{}/{}", api.host, api.owner, api.repo);
+    let response = http::get_cached(api_get(api.client, &url, api.token)).await?;
+
+    super::ensure_authorized(response.status, &response.headers, api.token)?;
+    if !response.status.is_success() {
+        return Ok(None);
+    }
+
+    let repo_info: GiteaRepoResponse = response.json()?;
+    Ok(Some(repo_info.default_branch))
+}
+
+pub(super) async fn lookup_ref(
+    api: &RepoApi<'_>,
+    kind: RefKind,
+    name: &str,
+) -> Result<Option<String>, RepoError> {
+    let collection = match kind {
+        RefKind::Branch => "branches",
+        RefKind::Tag => "tags",
+        RefKind::Commit => "git/commits",
+    };
+    let url = format!(
+        "https://{}/api/v1/repos/{}/{}/{}/{}",
+        api.host,
+        api.owner,
+        api.repo,
+        collection,
+        urlencoding::encode(name)
+    );
+    let response = http::send(api_get(api.client, &url, api.token)).await?;
+
+    super::ensure_authorized(response.status(), response.headers(), api.token)?;
+    if !response.status().is_success() {
+        return Ok(None);
+    }
+
+    let sha = match kind {
+        RefKind::Branch => response.json::<GiteaBranchResponse>().await?.commit.id,
+        RefKind::Tag => response.json::<GiteaTagResponse>().await?.commit.sha,
+        RefKind::Commit => response.json::<GiteaCommitResponse>().await?.sha,
+    };
+    Ok(Some(sha))
+}
+
+/// Names of branches or tags starting with `prefix`. Gitea has no server-side
+/// name filter, so this scans the first page of refs.
+pub(super) async fn list_refs(
+    api: &RepoApi<'_>,
+    kind: RefKind,
+    prefix: &str,
+) -> Result<Vec<String>, RepoError> {
+    let collection = if kind == RefKind::Tag { "tags" } else { "branches" };
+    let url = format!(
+        "https://{}/api/v1/repos/{}/{}/{}?limit=100",
+        api.host, api.owner, api.repo, collection
+    );
+    let response = http::send(api_get(api.client, &url, api.token)).await?;
+
+    super::ensure_authorized(response.status(), response.headers(), api.token)?;
+    if !response.status().is_success() {
+        return Ok(vec![]);
+    }
+
+    let refs: Vec<GiteaNamedRef> = response.json().await?;
+    Ok(refs
+        .into_iter()
+        .map(|r| r.name)
+        .filter(|name| name.starts_with(prefix))
+        .collect())
+}
+
+pub(super) async fn submodule_commit(
+    api: &RepoApi<'_>,
+    git_ref: &str,
+    path: &str,
+) -> Result<Option<String>, RepoError> {
+    let url = format!(
+        "https://{}/api/v1/repos/{}/{}/contents/{}?ref={}",
+        api.host,
+        api.owner,
+        api.repo,
+        path,
+        urlencoding::encode(git_ref)
+    );
+    let response = http::send(api_get(api.client, &url, api.token)).await?;
+
+    super::ensure_authorized(response.status(), response.headers(), api.token)?;
+    if !response.status().is_success() {
+        return Ok(None);
+    }
+
+    // Directories come back as an array of entries
+    let body: serde_json::Value = response.json().await?;
+    if body.is_array() {
+        return Ok(None);
+    }
+    let content: GiteaContentResponse = serde_json::from_value(body)?;
+    Ok((content.content_type == "submodule").then_some(content.sha))
+}
+
+pub(super) fn archive_url(api: &RepoApi<'_>, resolved: &ResolvedRef) -> String {
+    let archive_ref = resolved.commit_sha.as_deref().unwrap_or(&resolved.name);
+    format!(
+        "https://{}/api/v1/repos/{}/{}/archive/{}.zip",
+        api.host, api.owner, api.repo, archive_ref
+    )
+}
+
+pub(super) async fn search(
+    client: &reqwest::Client,
+    host: &str,
+    query: &str,
+    token: Option<&str>,
+) -> Result<Cached<Vec<SearchResultItem>>, RepoError> {
+    let url = format!(
+        "https://{}/api/v1/repos/search?q={}&limit=15&sort=stars&order=desc",
+        host,
+        urlencoding::encode(query)
+    );
+
+    let response = http::get_cached(api_get(client, &url, token)).await?;
+
+    if !response.status.is_success() {
+        return Err(RepoError::InvalidUrl(format!(
+            "Gitea API error: HTTP {}",
+            response.status
+        )));
+    }
+
+    let search_response: GiteaSearchResponse = response.json()?;
+
+    let results = search_response
+        .data
+        .into_iter()
+        .map(|item| SearchResultItem {
+            full_name: item.full_name,
+            description: item.description,
+            stargazers_count: item.stars_count,
+            html_url: item.html_url,
+            owner: item.owner.login,
+            repo: item.name,
+        })
+        .collect();
+
+    Ok(response.with(results))
+}
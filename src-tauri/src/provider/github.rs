@@ -0,0 +1,292 @@
+use serde::Deserialize;
+
+use super::{GitHubEndpoints, RefKind, RepoApi, RepoPath, ResolvedRef};
+use crate::http::{self, Cached};
+use crate::repo::{RepoError, SearchResultItem};
+
+#[derive(Debug, Deserialize)]
+struct GitHubRepoResponse {
+    default_branch: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubGitObject {
+    sha: String,
+    #[serde(rename = "type")]
+    object_type: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubGitRefResponse {
+    object: GitHubGitObject,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubMatchingRef {
+    #[serde(rename = "ref")]
+    full_ref: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubTagResponse {
+    object: GitHubGitObject,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubCommitResponse {
+    sha: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubContentResponse {
+    #[serde(rename = "type")]
+    content_type: String,
+    sha: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubSearchResponse {
+    items: Vec<GitHubSearchItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubSearchItem {
+    full_name: String,
+    description: Option<String>,
+    stargazers_count: u64,
+    html_url: String,
+    owner: GitHubOwner,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubOwner {
+    login: String,
+}
+
+fn api_base<'a>(api: &'a RepoApi<'_>) -> &'a str {
+    api.github.map_or("https://api.github.com", |e| e.api.as_str())
+}
+
+pub(super) fn authorize(request: reqwest::RequestBuilder, token: Option<&str>) -> reqwest::RequestBuilder {
+    match token {
+        Some(t) if !t.is_empty() => request.header("Authorization", format!("Bearer {}", t)),
+        _ => request,
+    }
+}
+
+fn api_get(client: &reqwest::Client, url: &str, token: Option<&str>) -> reqwest::RequestBuilder {
+    let request = client
+        .get(url)
+        .header("Accept", "application/vnd.github.v3+json");
+    authorize(request, token)
+}
+
+/// `owner/repo[/tree/<ref> | /commit/<sha> | /releases/tag/<tag>]`
+pub(super) fn parse_path(parts: &[&str]) -> Result<RepoPath, RepoError> {
+    if parts.len() < 2 {
+        return Err(RepoError::InvalidUrl("Missing owner or repo".into()));
+    }
+
+    let (git_ref, ref_kind) = match parts.get(2).copied() {
+        Some("tree") if parts.len() >= 4 => (Some(parts[3..].join("/")), None),
+        Some("commit") if parts.len() >= 4 => (Some(parts[3].to_string()), Some(RefKind::Commit)),
+        Some("releases") if parts.len() >= 5 && parts[3] == "tag" => {
+            (Some(parts[4..].join("/")), Some(RefKind::Tag))
+        }
+        _ => (None, None),
+    };
+
+    Ok(RepoPath {
+        owner: parts[0].to_string(),
+        repo: parts[1].to_string(),
+        git_ref,
+        ref_kind,
+    })
+}
+
+pub(super) async fn default_branch(api: &RepoApi<'_>) -> Result<Option<String>, RepoError> {
+    let url = format!("{}/repos/{}/{}", api_base(api), api.owner, api.repo);
+    let response = http::get_cached(api_get(api.client, &url, api.token)).await?;
+
+    super::ensure_authorized(response.status, &response.headers, api.token)?;
+    if !response.status.is_success() {
+        return Ok(None);
+    }
+
+    let repo_info: GitHubRepoResponse = response.json()?;
+    Ok(Some(repo_info.default_branch))
+}
+
+/// Returns the commit `name` points to, peeling annotated tags. None when it does not exist.
+pub(super) async fn lookup_ref(
+    api: &RepoApi<'_>,
+    kind: RefKind,
+    name: &str,
+) -> Result<Option<String>, RepoError> {
+    let base = format!("{}/repos/{}/{}", api_base(api), api.owner, api.repo);
+
+    if kind == RefKind::Commit {
+        let url = format!("{}/commits/{}", base, name);
+        let response = http::send(api_get(api.client, &url, api.token)).await?;
+        super::ensure_authorized(response.status(), response.headers(), api.token)?;
+        if !response.status().is_success() {
+            return Ok(None);
+        }
+        let commit: GitHubCommitResponse = response.json().await?;
+        return Ok(Some(commit.sha));
+    }
+
+    let namespace = if kind == RefKind::Tag { "tags" } else { "heads" };
+    let url = format!("{}/git/ref/{}/{}", base, namespace, name);
+    let response = http::send(api_get(api.client, &url, api.token)).await?;
+
+    super::ensure_authorized(response.status(), response.headers(), api.token)?;
+    if !response.status().is_success() {
+        return Ok(None);
+    }
+
+    // A prefix match returns an array of refs rather than the exact ref
+    let body: serde_json::Value = response.json().await?;
+    if body.is_array() {
+        return Ok(None);
+    }
+    let git_ref: GitHubGitRefResponse = serde_json::from_value(body)?;
+
+    if git_ref.object.object_type != "tag" {
+        return Ok(Some(git_ref.object.sha));
+    }
+
+    // Annotated tag: follow the tag object to the commit
+    let url = format!("{}/git/tags/{}", base, git_ref.object.sha);
+    let response = http::send(api_get(api.client, &url, api.token)).await?;
+
+    if !response.status().is_success() {
+        return Ok(None);
+    }
+
+    let tag: GitHubTagResponse = response.json().await?;
+    Ok(Some(tag.object.sha))
+}
+
+/// Names of branches or tags starting with `prefix`
+pub(super) async fn list_refs(
+    api: &RepoApi<'_>,
+    kind: RefKind,
+    prefix: &str,
+) -> Result<Vec<String>, RepoError> {
+    let namespace = if kind == RefKind::Tag { "tags" } else { "heads" };
+    let url = format!(
+        "{}/repos/{}/{}/git/matching-refs/{}/{}",
+        api_base(api),
+        api.owner,
+        api.repo,
+        namespace,
+        prefix
+    );
+    let response = http::send(api_get(api.client, &url, api.token)).await?;
+
+    super::ensure_authorized(response.status(), response.headers(), api.token)?;
+    if !response.status().is_success() {
+        return Ok(vec![]);
+    }
+
+    let refs: Vec<GitHubMatchingRef> = response.json().await?;
+    let strip = format!("refs/{}/", namespace);
+    Ok(refs
+        .into_iter()
+        .filter_map(|r| r.full_ref.strip_prefix(&strip).map(|n| n.to_string()))
+        .collect())
+}
+
+pub(super) async fn submodule_commit(
+    api: &RepoApi<'_>,
+    git_ref: &str,
+    path: &str,
+) -> Result<Option<String>, RepoError> {
+    let url = format!(
+        "{}/repos/{}/{}/contents/{}?ref={}",
+        api_base(api),
+        api.owner,
+        api.repo,
+        path,
+        urlencoding::encode(git_ref)
+    );
+    let response = http::send(api_get(api.client, &url, api.token)).await?;
+
+    super::ensure_authorized(response.status(), response.headers(), api.token)?;
+    if !response.status().is_success() {
+        return Ok(None);
+    }
+
+    // Directories come back as an array of entries
+    let body: serde_json::Value = response.json().await?;
+    if body.is_array() {
+        return Ok(None);
+    }
+    let content: GitHubContentResponse = serde_json::from_value(body)?;
+    Ok((content.content_type == "submodule").then_some(content.sha))
+}
+
+pub(super) fn archive_url(api: &RepoApi<'_>, resolved: &ResolvedRef) -> String {
+    let archive_ref = match (&resolved.commit_sha, resolved.kind) {
+        (Some(sha), _) => sha.clone(),
+        (None, RefKind::Branch) => format!("refs/heads/{}", resolved.name),
+        (None, RefKind::Tag) => format!("refs/tags/{}", resolved.name),
+        (None, RefKind::Commit) => resolved.name.clone(),
+    };
+
+    // codeload only serves public repos; the zipball API accepts a token and
+    // redirects to a signed codeload URL, which is how private repos are fetched
+    let codeload = api.github.and_then(|e| e.archive.as_deref());
+    if let (Some(base), None) = (codeload, api.token) {
+        format!("{}/{}/{}/zip/{}", base, api.owner, api.repo, archive_ref)
+    } else {
+        format!(
+            "{}/repos/{}/{}/zipball/{}",
+            api_base(api),
+            api.owner,
+            api.repo,
+            archive_ref
+        )
+    }
+}
+
+pub(super) async fn search(
+    client: &reqwest::Client,
+    endpoints: &GitHubEndpoints,
+    query: &str,
+    token: Option<&str>,
+) -> Result<Cached<Vec<SearchResultItem>>, RepoError> {
+    let url = format!(
+        "{}/search/repositories?q={}&per_page=15&sort=stars&order=desc",
+        endpoints.api,
+        urlencoding::encode(query)
+    );
+
+    let response = http::get_cached(api_get(client, &url, token)).await?;
+
+    if !response.status.is_success() {
+        return Err(RepoError::InvalidUrl(format!(
+            "GitHub API error: HTTP {}",
+            response.status
+        )));
+    }
+
+    let search_response: GitHubSearchResponse = response.json()?;
+
+    let results = search_response
+        .items
+        .into_iter()
+        .map(|item| SearchResultItem {
+            full_name: item.full_name,
+            description: item.description,
+            stargazers_count: item.stargazers_count,
+            html_url: item.html_url,
+            owner: item.owner.login,
+            repo: item.name,
+        })
+        .collect();
+
+    Ok(response.with(results))
+}
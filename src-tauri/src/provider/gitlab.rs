@@ -0,0 +1,245 @@
+use serde::Deserialize;
+
+use super::{RefKind, RepoApi, RepoPath, ResolvedRef};
+use crate::http::{self, Cached};
+use crate::repo::{RepoError, SearchResultItem};
+
+#[derive(Debug, Deserialize)]
+struct GitLabProjectResponse {
+    default_branch: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitLabCommit {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitLabRefResponse {
+    commit: GitLabCommit,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitLabNamedRef {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitLabTreeEntry {
+    id: String,
+    path: String,
+    #[serde(rename = "type")]
+    entry_type: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitLabNamespace {
+    full_path: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitLabSearchItem {
+    path_with_namespace: String,
+    description: Option<String>,
+    star_count: u64,
+    web_url: String,
+    namespace: GitLabNamespace,
+    path: String,
+}
+
+fn project_url(api: &RepoApi<'_>) -> String {
+    format!(
+        "https://{}/api/v4/projects/{}",
+        api.host,
+        urlencoding::encode(&format!("{}/{}", api.owner, api.repo))
+    )
+}
+
+pub(super) fn authorize(request: reqwest::RequestBuilder, token: Option<&str>) -> reqwest::RequestBuilder {
+    match token {
+        Some(t) if !t.is_empty() => request.header("PRIVATE-TOKEN", t),
+        _ => request,
+    }
+}
+
+fn api_get(client: &reqwest::Client, url: &str, token: Option<&str>) -> reqwest::RequestBuilder {
+    let request = client
+        .get(url)
+        .header("Accept", "application/json");
+    authorize(request, token)
+}
+
+/// `group[/subgroup...]/project[/-/tree/<ref> | /-/commit/<sha> | /-/tags/<tag> | /-/releases/<tag>]`
+pub(super) fn parse_path(parts: &[&str]) -> Result<RepoPath, RepoError> {
+    // Everything after the `-` separator describes a view inside the project
+    let (project, view) = match parts.iter().position(|p| *p == "-") {
+        Some(i) => (&parts[..i], &parts[i + 1..]),
+        None => (parts, &parts[parts.len()..]),
+    };
+
+    if project.len() < 2 {
+        return Err(RepoError::InvalidUrl("Missing group or project".into()));
+    }
+
+    let (git_ref, ref_kind) = match view.first().copied() {
+        Some("tree") if view.len() >= 2 => (Some(view[1..].join("/")), None),
+        Some("commit") if view.len() >= 2 => (Some(view[1].to_string()), Some(RefKind::Commit)),
+        Some("tags") | Some("releases") if view.len() >= 2 => {
+            (Some(view[1..].join("/")), Some(RefKind::Tag))
+        }
+        _ => (None, None),
+    };
+
+    Ok(RepoPath {
+        owner: project[..project.len() - 1].join("/"),
+        repo: project[project.len() - 1].to_string(),
+        git_ref,
+        ref_kind,
+    })
+}
+
+pub(super) async fn default_branch(api: &RepoApi<'_>) -> Result<Option<String>, RepoError> {
+    let response = http::get_cached(api_get(api.client, &project_url(api), api.token)).await?;
+
+    super::ensure_authorized(response.status, &response.headers, api.token)?;
+    if !response.status.is_success() {
+        return Ok(None);
+    }
+
+    let project: GitLabProjectResponse = response.json()?;
+    Ok(project.default_branch)
+}
+
+pub(super) async fn lookup_ref(
+    api: &RepoApi<'_>,
+    kind: RefKind,
+    name: &str,
+) -> Result<Option<String>, RepoError> {
+    let collection = match kind {
+        RefKind::Branch => "repository/branches",
+        RefKind::Tag => "repository/tags",
+        RefKind::Commit => "repository/commits",
+    };
+    let url = format!(
+        "{}/{}/{}",
+        project_url(api),
+        collection,
+        urlencoding::encode(name)
+    );
+    let response = http::send(api_get(api.client, &url, api.token)).await?;
+
+    super::ensure_authorized(response.status(), response.headers(), api.token)?;
+    if !response.status().is_success() {
+        return Ok(None);
+    }
+
+    if kind == RefKind::Commit {
+        let commit: GitLabCommit = response.json().await?;
+        Ok(Some(commit.id))
+    } else {
+        let git_ref: GitLabRefResponse = response.json().await?;
+        Ok(Some(git_ref.commit.id))
+    }
+}
+
+/// Names of branches or tags starting with `prefix`
+pub(super) async fn list_refs(
+    api: &RepoApi<'_>,
+    kind: RefKind,
+    prefix: &str,
+) -> Result<Vec<String>, RepoError> {
+    let collection = if kind == RefKind::Tag {
+        "repository/tags"
+    } else {
+        "repository/branches"
+    };
+    let url = format!(
+        "{}/{}?search=^{}&per_page=100",
+        project_url(api),
+        collection,
+        urlencoding::encode(prefix)
+    );
+    let response = http::send(api_get(api.client, &url, api.token)).await?;
+
+    super::ensure_authorized(response.status(), response.headers(), api.token)?;
+    if !response.status().is_success() {
+        return Ok(vec![]);
+    }
+
+    let refs: Vec<GitLabNamedRef> = response.json().await?;
+    Ok(refs.into_iter().map(|r| r.name).collect())
+}
+
+/// GitLab lists submodules as `commit` entries in the tree of their parent folder
+pub(super) async fn submodule_commit(
+    api: &RepoApi<'_>,
+    git_ref: &str,
+    path: &str,
+) -> Result<Option<String>, RepoError> {
+    let parent = path.rsplit_once('/').map_or("", |(parent, _)| parent);
+    let url = format!(
+        "{}/repository/tree?path={}&ref={}&per_page=100",
+        project_url(api),
+        urlencoding::encode(parent),
+        urlencoding::encode(git_ref)
+    );
+    let response = http::send(api_get(api.client, &url, api.token)).await?;
+
+    super::ensure_authorized(response.status(), response.headers(), api.token)?;
+    if !response.status().is_success() {
+        return Ok(None);
+    }
+
+    let entries: Vec<GitLabTreeEntry> = response.json().await?;
+    Ok(entries
+        .into_iter()
+        .find(|entry| entry.path == path && entry.entry_type == "commit")
+        .map(|entry| entry.id))
+}
+
+pub(super) fn archive_url(api: &RepoApi<'_>, resolved: &ResolvedRef) -> String {
+    let archive_ref = resolved.commit_sha.as_deref().unwrap_or(&resolved.name);
+    format!(
+        "{}/repository/archive.zip?sha={}",
+        project_url(api),
+        urlencoding::encode(archive_ref)
+    )
+}
+
+pub(super) async fn search(
+    client: &reqwest::Client,
+    host: &str,
+    query: &str,
+    token: Option<&str>,
+) -> Result<Cached<Vec<SearchResultItem>>, RepoError> {
+    let url = format!(
+        "https://{}/api/v4/projects?search={}&per_page=15&order_by=star_count&sort=desc",
+        host,
+        urlencoding::encode(query)
+    );
+
+    let response = http::get_cached(api_get(client, &url, token)).await?;
+
+    if !response.status.is_success() {
+        return Err(RepoError::InvalidUrl(format!(
+            "GitLab API error: HTTP {}",
+            response.status
+        )));
+    }
+
+    let items: Vec<GitLabSearchItem> = response.json()?;
+
+    let results = items
+        .into_iter()
+        .map(|item| SearchResultItem {
+            full_name: item.path_with_namespace,
+            description: item.description,
+            stargazers_count: item.star_count,
+            html_url: item.web_url,
+            owner: item.namespace.full_path,
+            repo: item.path,
+        })
+        .collect();
+
+    Ok(response.with(results))
+}
@@ -0,0 +1,927 @@
+//! Source hosting providers. Each provider module knows how to parse its web URLs,
+//! look up the default branch, resolve refs to commits, build archive download URLs
+//! and search repositories; this module dispatches to the right one.
+
+mod bitbucket;
+mod gitea;
+mod github;
+mod gitlab;
+mod lfs;
+
+pub use lfs::download_lfs_object;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use tokio::io::AsyncWriteExt;
+
+use crate::http::{self, Cached};
+use crate::repo::{get_downloads_dir, AppSettings, RepoError, RepoInfo, SearchResultItem};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    #[default]
+    GitHub,
+    GitLab,
+    Gitea,
+    Bitbucket,
+}
+
+impl ProviderKind {
+    /// Same name as the serialized form
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ProviderKind::GitHub => "github",
+            ProviderKind::GitLab => "gitlab",
+            ProviderKind::Gitea => "gitea",
+            ProviderKind::Bitbucket => "bitbucket",
+        }
+    }
+
+    pub fn default_host(self) -> &'static str {
+        match self {
+            ProviderKind::GitHub => "github.com",
+            ProviderKind::GitLab => "gitlab.com",
+            ProviderKind::Gitea => "codeberg.org",
+            ProviderKind::Bitbucket => "bitbucket.org",
+        }
+    }
+
+    /// Recognizes the public instances plus self-hosted GitLab/Gitea/Forgejo by hostname.
+    pub fn from_host(host: &str) -> Option<Self> {
+        let host = host.strip_prefix("www.").unwrap_or(host);
+        match host {
+            "github.com" => Some(ProviderKind::GitHub),
+            "gitlab.com" => Some(ProviderKind::GitLab),
+            "codeberg.org" | "gitea.com" => Some(ProviderKind::Gitea),
+            "bitbucket.org" => Some(ProviderKind::Bitbucket),
+            _ if host.contains("gitlab") => Some(ProviderKind::GitLab),
+            _ if host.contains("gitea") || host.contains("forgejo") => Some(ProviderKind::Gitea),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RefKind {
+    #[default]
+    Branch,
+    Tag,
+    Commit,
+}
+
+/// Base URLs of a GitHub instance
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitHubEndpoints {
+    pub web: String,
+    pub api: String,
+    /// codeload-style server for public archives, when the instance has one
+    pub archive: Option<String>,
+}
+
+impl GitHubEndpoints {
+    /// github.com's own endpoints, a configured instance's, or the Enterprise Server defaults
+    pub fn for_host(host: &str, settings: &AppSettings) -> Self {
+        let trim = |url: &String| url.trim_end_matches('/').to_string();
+        let (web, api, archive) = if host == ProviderKind::GitHub.default_host() {
+            (
+                "https://github.com".to_string(),
+                "https://api.github.com".to_string(),
+                Some("https://codeload.github.com".to_string()),
+            )
+        } else {
+            (format!("https://{}", host), format!("https://{}/api/v3", host), None)
+        };
+
+        match settings.github_host(host) {
+            Some(config) => Self {
+                web: config.web_url.as_ref().map(trim).unwrap_or(web),
+                api: config.api_url.as_ref().map(trim).unwrap_or(api),
+                archive: config.archive_url.as_ref().map(trim).or(archive),
+            },
+            None => Self { web, api, archive },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParsedRepoUrl {
+    pub provider: ProviderKind,
+    pub host: String,
+    pub owner: String,
+    pub repo: String,
+    pub git_ref: Option<String>,
+    /// Set when the URL shape pins the ref type (e.g. /commit/<sha>, /releases/tag/<tag>)
+    pub ref_kind: Option<RefKind>,
+    /// Where to reach the instance; only set for GitHub
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github: Option<GitHubEndpoints>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedRef {
+    pub kind: RefKind,
+    pub name: String,
+    pub commit_sha: Option<String>,
+    /// Folder inside the repo the URL pointed at, e.g. `packages/foo` in `/tree/main/packages/foo`
+    pub subpath: Option<String>,
+}
+
+impl ParsedRepoUrl {
+    /// Rebuilds the URL parts of an already imported repo, pinned to the ref it was imported from
+    pub fn from_repo_info(info: &RepoInfo, settings: &AppSettings) -> Self {
+        Self {
+            github: (info.provider == ProviderKind::GitHub).then(|| GitHubEndpoints::for_host(&info.host, settings)),
+            provider: info.provider,
+            host: info.host.clone(),
+            owner: info.owner.clone(),
+            repo: info.repo.clone(),
+            git_ref: Some(info.branch.clone()),
+            ref_kind: Some(info.ref_kind),
+        }
+    }
+}
+
+/// Everything a provider needs to call the API of one repository
+struct RepoApi<'a> {
+    client: &'a reqwest::Client,
+    host: &'a str,
+    owner: &'a str,
+    repo: &'a str,
+    token: Option<&'a str>,
+    github: Option<&'a GitHubEndpoints>,
+}
+
+impl<'a> RepoApi<'a> {
+    fn new(client: &'a reqwest::Client, parsed: &'a ParsedRepoUrl, token: Option<&'a str>) -> Self {
+        Self {
+            client,
+            github: parsed.github.as_ref(),
+            host: &parsed.host,
+            owner: &parsed.owner,
+            repo: &parsed.repo,
+            token: token.filter(|t| !t.is_empty()),
+        }
+    }
+}
+
+/// Owner/repo/ref portion of a URL as understood by a single provider
+struct RepoPath {
+    owner: String,
+    repo: String,
+    git_ref: Option<String>,
+    ref_kind: Option<RefKind>,
+}
+
+/// Parses a repository URL. Hosts listed in `settings.github_hosts` are
+/// recognized as GitHub instances in addition to the public hosts.
+pub fn parse_repo_url(url: &str, settings: &AppSettings) -> Result<ParsedRepoUrl, RepoError> {
+    let url = url.trim();
+    let url = url.split(['?', '#']).next().unwrap_or(url).trim_end_matches('/');
+
+    // Remove protocol
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .unwrap_or(url);
+
+    let (host, path) = rest
+        .split_once('/')
+        .ok_or_else(|| RepoError::InvalidUrl("Missing owner or repo".into()))?;
+    let host = host.to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host).to_string();
+
+    let provider = match settings.github_host(&host) {
+        Some(_) => ProviderKind::GitHub,
+        None => ProviderKind::from_host(&host)
+            .ok_or_else(|| RepoError::InvalidUrl(format!("Unsupported host: {}", host)))?,
+    };
+
+    let mut parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
+    // Accept clone URLs like https://host/owner/repo.git
+    if let Some(last) = parts.last_mut() {
+        if let Some(stripped) = last.strip_suffix(".git") {
+            *last = stripped;
+        }
+    }
+
+    let parsed = match provider {
+        ProviderKind::GitHub => github::parse_path(&parts)?,
+        ProviderKind::GitLab => gitlab::parse_path(&parts)?,
+        ProviderKind::Gitea => gitea::parse_path(&parts)?,
+        ProviderKind::Bitbucket => bitbucket::parse_path(&parts)?,
+    };
+
+    Ok(ParsedRepoUrl {
+        github: (provider == ProviderKind::GitHub).then(|| GitHubEndpoints::for_host(&host, settings)),
+        provider,
+        host,
+        owner: parsed.owner,
+        repo: parsed.repo,
+        git_ref: parsed.git_ref,
+        ref_kind: parsed.ref_kind,
+    })
+}
+
+/// Picks the credential to send for a parsed URL. The GitHub token is only ever
+/// sent to github.com and each configured instance gets its own, so no token
+/// can leak to another host.
+pub fn token_for(parsed: &ParsedRepoUrl, settings: &AppSettings) -> Option<String> {
+    if parsed.provider != ProviderKind::GitHub {
+        return None;
+    }
+    github_token_for(&parsed.host, settings)
+}
+
+/// Token for the GitHub instance at `host`
+pub fn github_token_for(host: &str, settings: &AppSettings) -> Option<String> {
+    let configured = settings.github_host(host).and_then(|h| h.token.clone());
+    let public = (host == ProviderKind::GitHub.default_host())
+        .then(|| settings.github_token.clone())
+        .flatten();
+    configured.filter(|t| !t.is_empty()).or(public.filter(|t| !t.is_empty()))
+}
+
+/// Maps responses that mean missing or rejected credentials to `RepoError::AuthError`.
+/// A 403 sent for a rate limit becomes `RepoError::RateLimited` instead.
+fn ensure_authorized(
+    status: reqwest::StatusCode,
+    headers: &reqwest::header::HeaderMap,
+    token: Option<&str>,
+) -> Result<(), RepoError> {
+    if let Some(error) = http::rate_limit_error(status, headers) {
+        return Err(error);
+    }
+    match status {
+        reqwest::StatusCode::UNAUTHORIZED if token.is_some() => Err(RepoError::AuthError(
+            "the configured access token was rejected".into(),
+        )),
+        reqwest::StatusCode::UNAUTHORIZED => Err(RepoError::AuthError(
+            "this repository requires an access token".into(),
+        )),
+        reqwest::StatusCode::FORBIDDEN if token.is_some() => Err(RepoError::AuthError(
+            "the configured access token does not grant access to this repository".into(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+pub async fn get_default_branch(parsed: &ParsedRepoUrl, token: Option<&str>) -> Result<String, RepoError> {
+    let client = http::client();
+    let api = RepoApi::new(&client, parsed, token);
+
+    let branch = match parsed.provider {
+        ProviderKind::GitHub => github::default_branch(&api).await?,
+        ProviderKind::GitLab => gitlab::default_branch(&api).await?,
+        ProviderKind::Gitea => gitea::default_branch(&api).await?,
+        ProviderKind::Bitbucket => bitbucket::default_branch(&api).await?,
+    };
+
+    // Fallback to "main" if API fails
+    Ok(branch.unwrap_or_else(|| "main".to_string()))
+}
+
+/// Turns a `.gitmodules` URL into an https repo URL. Handles scp-style SSH
+/// (`git@host:owner/repo.git`), `ssh://` and `git://` URLs, and paths relative
+/// to the parent repo (`../other.git`).
+pub fn resolve_submodule_url(parent: &ParsedRepoUrl, url: &str) -> String {
+    let url = url.trim();
+
+    if url.starts_with("./") || url.starts_with("../") {
+        let mut parts: Vec<&str> = parent.owner.split('/').collect();
+        parts.push(&parent.repo);
+        for segment in url.split('/') {
+            match segment {
+                "" | "." => {}
+                ".." => {
+                    parts.pop();
+                }
+                other => parts.push(other),
+            }
+        }
+        return format!("https://{}/{}", parent.host, parts.join("/"));
+    }
+
+    if let Some(rest) = url
+        .strip_prefix("ssh://")
+        .or_else(|| url.strip_prefix("git://"))
+        .or_else(|| url.strip_prefix("git+ssh://"))
+    {
+        let rest = rest.split_once('@').map_or(rest, |(_, r)| r);
+        let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+        // Drop an SSH port, the web UI is on the default one
+        let host = host.split(':').next().unwrap_or(host);
+        return format!("https://{}/{}", host, path);
+    }
+
+    if !url.contains("://") {
+        if let Some((user_host, path)) = url.split_once(':') {
+            let host = user_host.split_once('@').map_or(user_host, |(_, h)| h);
+            return format!("https://{}/{}", host, path.trim_start_matches('/'));
+        }
+    }
+
+    url.to_string()
+}
+
+/// Commit the submodule at `path` is pinned to in `git_ref` of the parent repo.
+/// None when the provider can't tell.
+pub async fn submodule_commit(
+    parsed: &ParsedRepoUrl,
+    git_ref: &str,
+    path: &str,
+    token: Option<&str>,
+) -> Result<Option<String>, RepoError> {
+    let client = http::client();
+    let api = RepoApi::new(&client, parsed, token);
+
+    match parsed.provider {
+        ProviderKind::GitHub => github::submodule_commit(&api, git_ref, path).await,
+        ProviderKind::GitLab => gitlab::submodule_commit(&api, git_ref, path).await,
+        ProviderKind::Gitea => gitea::submodule_commit(&api, git_ref, path).await,
+        // Bitbucket's source API doesn't report what a submodule is pinned to
+        ProviderKind::Bitbucket => Ok(None),
+    }
+}
+
+/// Commit the given branch or tag currently points to, or None if the API can't tell
+pub async fn current_commit_sha(
+    parsed: &ParsedRepoUrl,
+    kind: RefKind,
+    name: &str,
+    token: Option<&str>,
+) -> Result<Option<String>, RepoError> {
+    let client = http::client();
+    let api = RepoApi::new(&client, parsed, token);
+    lookup_ref(parsed.provider, &api, kind, name).await
+}
+
+async fn lookup_ref(
+    provider: ProviderKind,
+    api: &RepoApi<'_>,
+    kind: RefKind,
+    name: &str,
+) -> Result<Option<String>, RepoError> {
+    match provider {
+        ProviderKind::GitHub => github::lookup_ref(api, kind, name).await,
+        ProviderKind::GitLab => gitlab::lookup_ref(api, kind, name).await,
+        ProviderKind::Gitea => gitea::lookup_ref(api, kind, name).await,
+        ProviderKind::Bitbucket => bitbucket::lookup_ref(api, kind, name).await,
+    }
+}
+
+async fn list_refs(
+    provider: ProviderKind,
+    api: &RepoApi<'_>,
+    kind: RefKind,
+    prefix: &str,
+) -> Result<Vec<String>, RepoError> {
+    match provider {
+        ProviderKind::GitHub => github::list_refs(api, kind, prefix).await,
+        ProviderKind::GitLab => gitlab::list_refs(api, kind, prefix).await,
+        ProviderKind::Gitea => gitea::list_refs(api, kind, prefix).await,
+        ProviderKind::Bitbucket => bitbucket::list_refs(api, kind, prefix).await,
+    }
+}
+
+/// Splits `main/packages/foo` into a ref and a path inside the repo. Ref names can
+/// contain slashes too, so the longest branch or tag that is a whole-segment prefix
+/// wins. Without a match, the first segment is taken as the ref.
+async fn split_ref_and_path(
+    provider: ProviderKind,
+    api: &RepoApi<'_>,
+    ref_and_path: &str,
+    kind_hint: Option<RefKind>,
+) -> Result<(String, Option<String>, Option<RefKind>), RepoError> {
+    let first_segment = ref_and_path.split('/').next().unwrap_or(ref_and_path);
+    let kinds: &[RefKind] = match kind_hint {
+        Some(RefKind::Tag) => &[RefKind::Tag],
+        Some(RefKind::Branch) => &[RefKind::Branch],
+        _ => &[RefKind::Branch, RefKind::Tag],
+    };
+
+    let mut best: Option<(String, RefKind)> = None;
+    for &kind in kinds {
+        for name in unless_unavailable(list_refs(provider, api, kind, first_segment).await)? {
+            let matches = ref_and_path == name
+                || ref_and_path
+                    .strip_prefix(name.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'));
+            let longer = best.as_ref().is_none_or(|(b, _)| name.len() > b.len());
+            if matches && longer {
+                best = Some((name, kind));
+            }
+        }
+    }
+
+    let (name, kind) = match best {
+        Some((name, kind)) => (name, Some(kind)),
+        None => (first_segment.to_string(), kind_hint),
+    };
+    let subpath = ref_and_path[name.len()..].trim_matches('/');
+    let subpath = (!subpath.is_empty()).then(|| subpath.to_string());
+
+    Ok((name, subpath, kind))
+}
+
+fn looks_like_commit_sha(name: &str) -> bool {
+    (7..=40).contains(&name.len()) && name.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Lookups an import can do without. A rate limited or unreachable API
+/// answers like a ref that wasn't found.
+fn unless_unavailable<T: Default>(result: Result<T, RepoError>) -> Result<T, RepoError> {
+    match result {
+        Err(e) if e.is_transient() => Ok(T::default()),
+        result => result,
+    }
+}
+
+/// Works out whether the requested ref is a branch, tag or commit and which commit it
+/// points to. When the API can't answer (rate limits, network), the ref is returned
+/// unresolved and the archive download decides. The default branch can't be
+/// guessed, so failing to look that up fails.
+pub async fn resolve_ref(parsed: &ParsedRepoUrl, token: Option<&str>) -> Result<ResolvedRef, RepoError> {
+    let client = http::client();
+    let api = RepoApi::new(&client, parsed, token);
+
+    let (name, subpath, kind_hint) = match &parsed.git_ref {
+        Some(git_ref) if git_ref.contains('/') && parsed.ref_kind != Some(RefKind::Commit) => {
+            split_ref_and_path(parsed.provider, &api, git_ref, parsed.ref_kind).await?
+        }
+        Some(git_ref) => (git_ref.clone(), None, parsed.ref_kind),
+        None => (get_default_branch(parsed, token).await?, None, None),
+    };
+
+    let candidates: &[RefKind] = match kind_hint {
+        Some(RefKind::Branch) => &[RefKind::Branch],
+        Some(RefKind::Tag) => &[RefKind::Tag],
+        Some(RefKind::Commit) => &[],
+        None => &[RefKind::Branch, RefKind::Tag],
+    };
+
+    for &kind in candidates {
+        if let Some(sha) = unless_unavailable(lookup_ref(parsed.provider, &api, kind, &name).await)? {
+            return Ok(ResolvedRef {
+                kind,
+                name,
+                commit_sha: Some(sha),
+                subpath,
+            });
+        }
+    }
+
+    if kind_hint == Some(RefKind::Commit) || looks_like_commit_sha(&name) {
+        if let Some(sha) = unless_unavailable(lookup_ref(parsed.provider, &api, RefKind::Commit, &name).await)? {
+            return Ok(ResolvedRef {
+                kind: RefKind::Commit,
+                name: sha.clone(),
+                commit_sha: Some(sha),
+                subpath,
+            });
+        }
+    }
+
+    let kind = kind_hint.unwrap_or(if looks_like_commit_sha(&name) {
+        RefKind::Commit
+    } else {
+        RefKind::Branch
+    });
+
+    Ok(ResolvedRef {
+        kind,
+        name,
+        commit_sha: None,
+        subpath,
+    })
+}
+
+fn archive_url(provider: ProviderKind, api: &RepoApi<'_>, resolved: &ResolvedRef) -> String {
+    match provider {
+        ProviderKind::GitHub => github::archive_url(api, resolved),
+        ProviderKind::GitLab => gitlab::archive_url(api, resolved),
+        ProviderKind::Gitea => gitea::archive_url(api, resolved),
+        ProviderKind::Bitbucket => bitbucket::archive_url(api, resolved),
+    }
+}
+
+fn authorize(
+    provider: ProviderKind,
+    request: reqwest::RequestBuilder,
+    token: Option<&str>,
+) -> reqwest::RequestBuilder {
+    match provider {
+        ProviderKind::GitHub => github::authorize(request, token),
+        ProviderKind::GitLab => gitlab::authorize(request, token),
+        ProviderKind::Gitea => gitea::authorize(request, token),
+        ProviderKind::Bitbucket => bitbucket::authorize(request, token),
+    }
+}
+
+// Emit progress at most once per this many bytes to avoid flooding the UI
+const PROGRESS_STEP_BYTES: u64 = 256 * 1024;
+
+/// What was known about an archive when its download started, kept next to the
+/// partial file so a resumed download can prove it's still the same archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PartialDownload {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl PartialDownload {
+    /// Value for `If-Range`. Weak ETags don't guarantee identical bytes, so they can't be used.
+    fn validator(&self) -> Option<&str> {
+        self.etag
+            .as_deref()
+            .filter(|etag| !etag.starts_with("W/"))
+            .or(self.last_modified.as_deref())
+    }
+}
+
+/// Partial file and its metadata for a download, named by a hash of the URL
+fn partial_download_paths(url: &str) -> (PathBuf, PathBuf) {
+    use sha2::{Digest, Sha256};
+
+    let name = format!("{:x}", Sha256::digest(url.as_bytes()));
+    let dir = get_downloads_dir();
+    (dir.join(format!("{}.part", &name[..16])), dir.join(format!("{}.json", &name[..16])))
+}
+
+/// URLs whose shared partial file a download is currently writing
+fn downloads_in_progress() -> &'static Mutex<HashSet<String>> {
+    static IN_PROGRESS: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+    IN_PROGRESS.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+/// Use of a URL's shared partial file, given up when dropped
+struct PartialDownloadClaim(String);
+
+impl PartialDownloadClaim {
+    fn acquire(url: &str) -> Option<Self> {
+        downloads_in_progress()
+            .lock()
+            .unwrap()
+            .insert(url.to_string())
+            .then(|| Self(url.to_string()))
+    }
+}
+
+impl Drop for PartialDownloadClaim {
+    fn drop(&mut self) {
+        downloads_in_progress().lock().unwrap().remove(&self.0);
+    }
+}
+
+fn header_value(response: &reqwest::Response, name: reqwest::header::HeaderName) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string())
+}
+
+/// Start offset of a `Content-Range: bytes <start>-<end>/<total>` header
+fn content_range_start(response: &reqwest::Response) -> Option<u64> {
+    let range = header_value(response, reqwest::header::CONTENT_RANGE)?;
+    range.strip_prefix("bytes ")?.split('-').next()?.trim().parse().ok()
+}
+
+/// How often a download waiting on the network checks whether it was cancelled
+const CANCEL_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Resolves once `cancel` is set
+async fn cancelled(cancel: &AtomicBool) {
+    while !cancel.load(Ordering::SeqCst) {
+        tokio::time::sleep(CANCEL_POLL_INTERVAL).await;
+    }
+}
+
+/// Downloads the archive to `dest_path`. An interrupted download is kept under
+/// the downloads dir and resumed with a `Range` request on the next attempt,
+/// guarded by `If-Range` so bytes from two different archives are never joined.
+/// When another import is already downloading the same archive, this one
+/// writes a private file next to `dest_path` instead and can't be resumed.
+pub async fn download_repo_zip<F>(
+    parsed: &ParsedRepoUrl,
+    resolved: &ResolvedRef,
+    token: Option<&str>,
+    dest_path: &Path,
+    cancel: &AtomicBool,
+    mut on_progress: F,
+) -> Result<(), RepoError>
+where
+    F: FnMut(u64, Option<u64>),
+{
+    let client = http::client();
+    let api = RepoApi::new(&client, parsed, token);
+    let zip_url = archive_url(parsed.provider, &api, resolved);
+
+    let claim = PartialDownloadClaim::acquire(&zip_url);
+    let (partial_path, meta_path) = match claim {
+        Some(_) => partial_download_paths(&zip_url),
+        None => (dest_path.with_extension("part"), dest_path.with_extension("part.json")),
+    };
+    let previous: Option<PartialDownload> = fs::read_to_string(&meta_path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .filter(|p: &PartialDownload| p.url == zip_url);
+    let partial_len = fs::metadata(&partial_path).map(|m| m.len()).unwrap_or(0);
+
+    let validator = previous.as_ref().and_then(|p| p.validator());
+    let mut resume_from = if validator.is_some() { partial_len } else { 0 };
+    let mut response = loop {
+        let mut request = client.get(&zip_url);
+        if let Some(validator) = validator.filter(|_| resume_from > 0) {
+            request = request
+                .header(reqwest::header::RANGE, format!("bytes={}-", resume_from))
+                .header(reqwest::header::IF_RANGE, validator);
+        }
+        let response = tokio::select! {
+            response = http::send(authorize(parsed.provider, request, api.token)) => response?,
+            _ = cancelled(cancel) => return Err(RepoError::Cancelled),
+        };
+
+        // The partial file is unusable (e.g. longer than the archive); fetch it whole
+        if response.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE && resume_from > 0 {
+            resume_from = 0;
+            continue;
+        }
+        break response;
+    };
+
+    ensure_authorized(response.status(), response.headers(), api.token)?;
+    // Hosts answer 404 rather than 401 for private repos when no token is sent
+    if response.status() == reqwest::StatusCode::NOT_FOUND && api.token.is_none() {
+        return Err(RepoError::AuthError(format!(
+            "{}/{} was not found; if it is private, add an access token in Settings",
+            parsed.owner, parsed.repo
+        )));
+    }
+    if !response.status().is_success() {
+        return Err(RepoError::HttpStatus(response.status().as_u16()));
+    }
+
+    // 206 means the server agreed the archive is unchanged and sent the rest.
+    // Anything else is the whole archive, so the partial file is replaced.
+    let resumed = resume_from > 0
+        && response.status() == reqwest::StatusCode::PARTIAL_CONTENT
+        && content_range_start(&response) == Some(resume_from);
+
+    fs::create_dir_all(get_downloads_dir())?;
+    let mut file = if resumed {
+        tokio::fs::OpenOptions::new().append(true).open(&partial_path).await?
+    } else {
+        let meta = PartialDownload {
+            url: zip_url.clone(),
+            etag: header_value(&response, reqwest::header::ETAG),
+            last_modified: header_value(&response, reqwest::header::LAST_MODIFIED),
+        };
+        fs::write(&meta_path, serde_json::to_string(&meta)?)?;
+        tokio::fs::File::create(&partial_path).await?
+    };
+
+    let offset = if resumed { resume_from } else { 0 };
+    let total = response.content_length().map(|len| len + offset);
+    let mut received: u64 = offset;
+    let mut last_reported: u64 = offset;
+
+    on_progress(received, total);
+
+    // Stream chunks straight to disk instead of buffering the whole archive
+    let result: Result<(), RepoError> = async {
+        // A stalled server can't hold up a cancel until the next chunk or the read timeout
+        while let Some(chunk) = tokio::select! {
+            chunk = response.chunk() => chunk?,
+            _ = cancelled(cancel) => return Err(RepoError::Cancelled),
+        } {
+            file.write_all(&chunk).await?;
+            received += chunk.len() as u64;
+            if received - last_reported >= PROGRESS_STEP_BYTES {
+                last_reported = received;
+                on_progress(received, total);
+            }
+        }
+        file.flush().await?;
+        Ok(())
+    }
+    .await;
+    drop(file);
+
+    match result {
+        Ok(()) => {}
+        // A cancelled download isn't coming back, so don't keep it around
+        Err(RepoError::Cancelled) => {
+            let _ = fs::remove_file(&partial_path);
+            let _ = fs::remove_file(&meta_path);
+            return Err(RepoError::Cancelled);
+        }
+        // Keep what arrived; the next attempt picks up from here. A private
+        // file can't be found again, so it goes.
+        Err(e) => {
+            if claim.is_none() {
+                let _ = fs::remove_file(&partial_path);
+                let _ = fs::remove_file(&meta_path);
+            }
+            return Err(e);
+        }
+    }
+
+    if let Some(parent) = dest_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(&partial_path, dest_path).is_err() {
+        // Different filesystems; fall back to copying
+        fs::copy(&partial_path, dest_path)?;
+        let _ = fs::remove_file(&partial_path);
+    }
+    let _ = fs::remove_file(&meta_path);
+
+    on_progress(received, total);
+
+    Ok(())
+}
+
+pub async fn search_repos(
+    provider: ProviderKind,
+    host: Option<&str>,
+    query: &str,
+    token: Option<&str>,
+    settings: &AppSettings,
+) -> Result<Cached<Vec<SearchResultItem>>, RepoError> {
+    if query.trim().is_empty() {
+        return Ok(Cached {
+            data: vec![],
+            stale: false,
+            fetched_at: chrono::Utc::now(),
+        });
+    }
+
+    let client = http::client();
+    let host = host.unwrap_or_else(|| provider.default_host());
+
+    match provider {
+        ProviderKind::GitHub => {
+            let endpoints = GitHubEndpoints::for_host(host, settings);
+            github::search(&client, &endpoints, query, token).await
+        }
+        ProviderKind::GitLab => gitlab::search(&client, host, query, token).await,
+        ProviderKind::Gitea => gitea::search(&client, host, query, token).await,
+        // Bitbucket Cloud's API has no public, cross-workspace repository search
+        ProviderKind::Bitbucket => Err(RepoError::InvalidUrl(
+            "Repository search is not supported for Bitbucket".into(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::GitHubHost;
+
+    fn parse(url: &str) -> ParsedRepoUrl {
+        parse_repo_url(url, &AppSettings::default()).unwrap()
+    }
+
+    #[test]
+    fn parses_plain_and_clone_urls() {
+        for url in [
+            "https://github.com/rust-lang/rust",
+            "http://www.github.com/rust-lang/rust/",
+            "github.com/rust-lang/rust.git",
+            "  https://GitHub.com/rust-lang/rust?tab=readme#top  ",
+        ] {
+            let parsed = parse(url);
+            assert_eq!(parsed.provider, ProviderKind::GitHub, "{}", url);
+            assert_eq!(parsed.host, "github.com");
+            assert_eq!((parsed.owner.as_str(), parsed.repo.as_str()), ("rust-lang", "rust"));
+            assert_eq!(parsed.git_ref, None);
+        }
+    }
+
+    #[test]
+    fn parses_refs_from_github_urls() {
+        let parsed = parse("https://github.com/o/r/tree/feature/x/src");
+        assert_eq!(parsed.git_ref.as_deref(), Some("feature/x/src"));
+        assert_eq!(parsed.ref_kind, None);
+
+        let parsed = parse("https://github.com/o/r/commit/abc123");
+        assert_eq!(parsed.git_ref.as_deref(), Some("abc123"));
+        assert_eq!(parsed.ref_kind, Some(RefKind::Commit));
+
+        let parsed = parse("https://github.com/o/r/releases/tag/v1.0");
+        assert_eq!(parsed.git_ref.as_deref(), Some("v1.0"));
+        assert_eq!(parsed.ref_kind, Some(RefKind::Tag));
+    }
+
+    #[test]
+    fn parses_other_providers() {
+        let parsed = parse("https://gitlab.com/group/sub/project/-/tree/main");
+        assert_eq!(parsed.provider, ProviderKind::GitLab);
+        assert_eq!((parsed.owner.as_str(), parsed.repo.as_str()), ("group/sub", "project"));
+        assert_eq!(parsed.git_ref.as_deref(), Some("main"));
+
+        let parsed = parse("https://codeberg.org/o/r/src/tag/v2");
+        assert_eq!(parsed.provider, ProviderKind::Gitea);
+        assert_eq!(parsed.git_ref.as_deref(), Some("v2"));
+        assert_eq!(parsed.ref_kind, Some(RefKind::Tag));
+
+        let parsed = parse("https://bitbucket.org/team/r/commits/abc");
+        assert_eq!(parsed.provider, ProviderKind::Bitbucket);
+        assert_eq!(parsed.ref_kind, Some(RefKind::Commit));
+
+        assert_eq!(parse("https://git.forgejo.example/o/r").provider, ProviderKind::Gitea);
+        assert_eq!(parse("https://gitlab.example.com/o/r").provider, ProviderKind::GitLab);
+        assert!(parsed.github.is_none());
+    }
+
+    #[test]
+    fn rejects_unsupported_and_incomplete_urls() {
+        let settings = AppSettings::default();
+        for url in ["https://example.com/o/r", "https://github.com/owner", "https://github.com", "", "github.com/"] {
+            assert!(
+                matches!(parse_repo_url(url, &settings), Err(RepoError::InvalidUrl(_))),
+                "{} was accepted",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn configured_github_hosts_use_their_endpoints_and_token() {
+        let settings = AppSettings {
+            github_token: Some("public".into()),
+            github_hosts: vec![GitHubHost {
+                host: "git.corp.example".into(),
+                api_url: Some("https://api.corp.example/".into()),
+                token: Some("corp".into()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let parsed = parse_repo_url("https://git.corp.example/team/tool", &settings).unwrap();
+        assert_eq!(parsed.provider, ProviderKind::GitHub);
+        let endpoints = parsed.github.as_ref().unwrap();
+        assert_eq!(endpoints.web, "https://git.corp.example");
+        assert_eq!(endpoints.api, "https://api.corp.example");
+        assert_eq!(endpoints.archive, None);
+        assert_eq!(token_for(&parsed, &settings).as_deref(), Some("corp"));
+
+        let public = parse_repo_url("https://github.com/o/r", &settings).unwrap();
+        assert_eq!(public.github.as_ref().unwrap().archive.as_deref(), Some("https://codeload.github.com"));
+        assert_eq!(token_for(&public, &settings).as_deref(), Some("public"));
+
+        // The public token never goes to another host
+        let gitlab = parse_repo_url("https://gitlab.com/o/r", &settings).unwrap();
+        assert_eq!(token_for(&gitlab, &settings), None);
+    }
+
+    #[test]
+    fn ensure_authorized_tells_rate_limits_from_denied_access() {
+        use reqwest::header::{HeaderMap, HeaderValue};
+        use reqwest::StatusCode;
+
+        let none = HeaderMap::new();
+        assert!(ensure_authorized(StatusCode::OK, &none, Some("t")).is_ok());
+        assert!(ensure_authorized(StatusCode::FORBIDDEN, &none, None).is_ok());
+        assert!(matches!(
+            ensure_authorized(StatusCode::FORBIDDEN, &none, Some("t")),
+            Err(RepoError::AuthError(_))
+        ));
+        assert!(matches!(
+            ensure_authorized(StatusCode::UNAUTHORIZED, &none, None),
+            Err(RepoError::AuthError(_))
+        ));
+
+        let mut exhausted = HeaderMap::new();
+        exhausted.insert("x-ratelimit-remaining", HeaderValue::from_static("0"));
+        exhausted.insert("x-ratelimit-reset", HeaderValue::from_static("4102444800"));
+        match ensure_authorized(StatusCode::FORBIDDEN, &exhausted, Some("t")) {
+            Err(RepoError::RateLimited { reset_at }) => {
+                assert_eq!(reset_at.map(|at| at.timestamp()), Some(4102444800))
+            }
+            other => panic!("expected a rate limit, got {:?}", other),
+        }
+
+        let mut secondary = HeaderMap::new();
+        secondary.insert("retry-after", HeaderValue::from_static("60"));
+        assert!(matches!(
+            ensure_authorized(StatusCode::FORBIDDEN, &secondary, Some("t")),
+            Err(RepoError::RateLimited { .. })
+        ));
+
+        let mut gitlab = HeaderMap::new();
+        gitlab.insert("ratelimit-remaining", HeaderValue::from_static("0"));
+        assert!(matches!(
+            ensure_authorized(StatusCode::FORBIDDEN, &gitlab, None),
+            Err(RepoError::RateLimited { .. })
+        ));
+    }
+}
@@ -0,0 +1,303 @@
+//! Background queue for importing many repositories at once. Runs a bounded
+//! number of imports concurrently and retries transient failures, waiting
+//! out rate limits until they reset; the import
+//! itself is passed in, so this stays independent of tauri.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{Notify, Semaphore};
+
+use crate::repo::{cancel_import, RepoError};
+
+/// Attempts per item, including the first
+const MAX_ATTEMPTS: u32 = 3;
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+/// Rate limits lifting later than this fail the item instead of holding up the batch
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(15 * 60);
+/// Finished batches kept for status queries; older ones are dropped
+const MAX_FINISHED_BATCHES: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchItemState {
+    Queued,
+    Running,
+    Retrying,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchItemStatus {
+    pub url: String,
+    pub state: BatchItemState,
+    pub attempts: u32,
+    pub repo_key: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchStatus {
+    pub batch_id: String,
+    pub items: Vec<BatchItemStatus>,
+    pub finished: bool,
+}
+
+/// Set when a batch is cancelled, waking items waiting to retry
+#[derive(Default)]
+struct CancelSignal {
+    cancelled: AtomicBool,
+    wake: Notify,
+}
+
+impl CancelSignal {
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.wake.notify_waiters();
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once the batch is cancelled
+    async fn cancelled(&self) {
+        let mut notified = std::pin::pin!(self.wake.notified());
+        // Registered before the flag is checked, so a cancel in between isn't missed
+        notified.as_mut().enable();
+        if !self.is_cancelled() {
+            notified.await;
+        }
+    }
+}
+
+struct Batch {
+    status: BatchStatus,
+    cancel: Arc<CancelSignal>,
+    /// Keys of the batch's imports in flight, cancelled along with it
+    running: HashSet<String>,
+    finished_at: Option<Instant>,
+}
+
+fn batches() -> &'static Mutex<HashMap<String, Batch>> {
+    static BATCHES: OnceLock<Mutex<HashMap<String, Batch>>> = OnceLock::new();
+    BATCHES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Registers a batch with every item queued. Duplicate URLs are only imported once.
+pub fn create_batch(urls: Vec<String>) -> BatchStatus {
+    let mut seen = std::collections::HashSet::new();
+    let items = urls
+        .into_iter()
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty() && seen.insert(url.clone()))
+        .map(|url| BatchItemStatus {
+            url,
+            state: BatchItemState::Queued,
+            attempts: 0,
+            repo_key: None,
+            error: None,
+        })
+        .collect();
+
+    let status = BatchStatus {
+        batch_id: format!("batch-{}", chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()),
+        items,
+        finished: false,
+    };
+    batches().lock().unwrap().insert(
+        status.batch_id.clone(),
+        Batch {
+            status: status.clone(),
+            cancel: Arc::new(CancelSignal::default()),
+            running: HashSet::new(),
+            finished_at: None,
+        },
+    );
+    status
+}
+
+pub fn batch_status(batch_id: &str) -> Option<BatchStatus> {
+    batches().lock().unwrap().get(batch_id).map(|b| b.status.clone())
+}
+
+/// Stops a batch from starting further items and cancels the imports it has running
+pub fn cancel_batch(batch_id: &str) -> bool {
+    match batches().lock().unwrap().get(batch_id) {
+        Some(batch) => {
+            batch.cancel.cancel();
+            for repo_key in &batch.running {
+                cancel_import(repo_key);
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Records that an item of the batch is importing `repo_key`, so cancelling the
+/// batch cancels it too. Returns false if the batch was cancelled already, in
+/// which case the import should not go ahead.
+pub fn import_started(batch_id: &str, repo_key: &str) -> bool {
+    let mut batches = batches().lock().unwrap();
+    let Some(batch) = batches.get_mut(batch_id) else {
+        return true;
+    };
+    if batch.cancel.is_cancelled() {
+        return false;
+    }
+    batch.running.insert(repo_key.to_string());
+    true
+}
+
+pub fn import_finished(batch_id: &str, repo_key: &str) {
+    if let Some(batch) = batches().lock().unwrap().get_mut(batch_id) {
+        batch.running.remove(repo_key);
+    }
+}
+
+/// Marks a batch finished and drops the oldest finished batches beyond
+/// `MAX_FINISHED_BATCHES`. Returns the batch's final status.
+fn finish(batch_id: &str) -> Option<BatchStatus> {
+    let mut batches = batches().lock().unwrap();
+    let batch = batches.get_mut(batch_id)?;
+    batch.status.finished = true;
+    batch.finished_at = Some(Instant::now());
+    let status = batch.status.clone();
+
+    let mut finished: Vec<(Instant, String)> = batches
+        .iter()
+        .filter_map(|(id, batch)| batch.finished_at.map(|at| (at, id.clone())))
+        .collect();
+    if finished.len() > MAX_FINISHED_BATCHES {
+        finished.sort();
+        for (_, id) in &finished[..finished.len() - MAX_FINISHED_BATCHES] {
+            batches.remove(id);
+        }
+    }
+    Some(status)
+}
+
+/// Applies `change` to a batch and returns a snapshot of it
+fn update(batch_id: &str, change: impl FnOnce(&mut BatchStatus)) -> Option<BatchStatus> {
+    let mut batches = batches().lock().unwrap();
+    let batch = batches.get_mut(batch_id)?;
+    change(&mut batch.status);
+    Some(batch.status.clone())
+}
+
+fn set_item(
+    batch_id: &str,
+    index: usize,
+    state: BatchItemState,
+    attempts: u32,
+    repo_key: Option<String>,
+    error: Option<String>,
+) -> Option<BatchStatus> {
+    update(batch_id, |status| {
+        if let Some(item) = status.items.get_mut(index) {
+            item.state = state;
+            item.attempts = attempts;
+            item.repo_key = repo_key.or(item.repo_key.take());
+            item.error = error;
+        }
+    })
+}
+
+/// Runs every item of a registered batch through `import`, at most `concurrency`
+/// at a time. `import` returns the key of the imported repo. `on_update` gets a
+/// snapshot of the batch after each item changes state.
+pub async fn run_batch<F, Fut, U>(batch_id: String, concurrency: usize, import: F, on_update: U)
+where
+    F: Fn(String) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<String, RepoError>> + Send + 'static,
+    U: Fn(&BatchStatus) + Send + Sync + 'static,
+{
+    let (urls, cancel) = match batches().lock().unwrap().get(&batch_id) {
+        Some(batch) => (
+            batch.status.items.iter().map(|i| i.url.clone()).collect::<Vec<_>>(),
+            batch.cancel.clone(),
+        ),
+        None => return,
+    };
+
+    let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+    let import = Arc::new(import);
+    let on_update = Arc::new(on_update);
+    let mut tasks = tokio::task::JoinSet::new();
+
+    for (index, url) in urls.into_iter().enumerate() {
+        let semaphore = semaphore.clone();
+        let import = import.clone();
+        let on_update = on_update.clone();
+        let cancel = cancel.clone();
+        let batch_id = batch_id.clone();
+
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire().await;
+            let notify = |status: Option<BatchStatus>| {
+                if let Some(status) = status {
+                    on_update(&status);
+                }
+            };
+
+            for attempt in 1..=MAX_ATTEMPTS {
+                if cancel.is_cancelled() {
+                    notify(set_item(&batch_id, index, BatchItemState::Cancelled, attempt - 1, None, None));
+                    return;
+                }
+
+                notify(set_item(&batch_id, index, BatchItemState::Running, attempt, None, None));
+                match import(url.clone()).await {
+                    Ok(repo_key) => {
+                        notify(set_item(&batch_id, index, BatchItemState::Done, attempt, Some(repo_key), None));
+                        return;
+                    }
+                    Err(e)
+                        if e.is_transient()
+                            && attempt < MAX_ATTEMPTS
+                            && e.retry_delay().is_none_or(|wait| wait <= MAX_RATE_LIMIT_WAIT) =>
+                    {
+                        notify(set_item(
+                            &batch_id,
+                            index,
+                            BatchItemState::Retrying,
+                            attempt,
+                            None,
+                            Some(e.to_string()),
+                        ));
+                        // A rate limit is waited out until it resets, unless the batch is cancelled first
+                        let backoff = RETRY_BASE_DELAY * 2u32.pow(attempt - 1);
+                        let delay = e.retry_delay().map_or(backoff, |wait| wait.max(backoff));
+                        tokio::select! {
+                            _ = tokio::time::sleep(delay) => {}
+                            _ = cancel.cancelled() => {
+                                notify(set_item(&batch_id, index, BatchItemState::Cancelled, attempt, None, None));
+                                return;
+                            }
+                        }
+                    }
+                    Err(RepoError::Cancelled) => {
+                        notify(set_item(&batch_id, index, BatchItemState::Cancelled, attempt, None, None));
+                        return;
+                    }
+                    Err(e) => {
+                        notify(set_item(&batch_id, index, BatchItemState::Failed, attempt, None, Some(e.to_string())));
+                        return;
+                    }
+                }
+            }
+        });
+    }
+
+    while tasks.join_next().await.is_some() {}
+
+    if let Some(status) = finish(&batch_id) {
+        on_update(&status);
+    }
+}
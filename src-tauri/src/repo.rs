@@ -1,17 +1,27 @@
 use serde::{Deserialize, Serialize};
 use scraper::{Html, Selector};
+use std::collections::HashMap;
 use std::fs::{self, File};
-use std::io::{self, Read, Write};
+use std::io::{self, Read};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 use thiserror::Error;
 
+use crate::http::{self, Cached};
+use crate::ignore::TreeFilter;
+use crate::paths;
+use crate::provider::{ProviderKind, RefKind};
+use crate::stats::{self, ContentStats, FileClass, FileScanner};
+use crate::tree_store::{self, TreePage};
+
 const MAX_FILE_SIZE: u64 = 3 * 1024 * 1024; // 3MB
 const MAX_LINES: usize = 50_000;
 const PREVIEW_LINES: usize = 1000;
 
 #[derive(Error, Debug)]
 pub enum RepoError {
-    #[error("Invalid GitHub URL: {0}")]
+    #[error("Invalid repository URL: {0}")]
     InvalidUrl(String),
     #[error("HTTP request failed: {0}")]
     HttpError(#[from] reqwest::Error),
@@ -23,6 +33,43 @@ pub enum RepoError {
     JsonError(#[from] serde_json::Error),
     #[error("Repository not found: {0}")]
     RepoNotFound(String),
+    #[error("Import cancelled")]
+    Cancelled,
+    #[error("Authentication failed: {0}")]
+    AuthError(String),
+    #[error("Archive rejected: {0}")]
+    UnsafeArchive(String),
+    #[error("Git LFS download failed: {0}")]
+    LfsError(String),
+    #[error("Download failed: HTTP {0}")]
+    HttpStatus(u16),
+    #[error("Rate limit exceeded{}", .reset_at.map(|t| format!(", resets at {}", t.to_rfc3339())).unwrap_or_default())]
+    RateLimited { reset_at: Option<chrono::DateTime<chrono::Utc>> },
+    #[error("Storage move failed: {0}")]
+    StorageMove(String),
+    /// Names the job, e.g. "An import of <key>"
+    #[error("{0} is still running")]
+    ImportRunning(String),
+}
+
+impl RepoError {
+    /// Failures worth retrying: dropped connections, timeouts, rate limits and server-side errors
+    pub fn is_transient(&self) -> bool {
+        match self {
+            RepoError::HttpError(e) => e.is_timeout() || e.is_connect() || e.is_request() || e.is_body(),
+            RepoError::HttpStatus(status) => *status == 429 || *status >= 500,
+            RepoError::RateLimited { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// How long the server asked us to wait before trying again, if it said
+    pub fn retry_delay(&self) -> Option<std::time::Duration> {
+        match self {
+            RepoError::RateLimited { reset_at: Some(at) } => (*at - chrono::Utc::now()).to_std().ok(),
+            _ => None,
+        }
+    }
 }
 
 impl Serialize for RepoError {
@@ -34,11 +81,14 @@ impl Serialize for RepoError {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ParsedGitHubUrl {
-    pub owner: String,
-    pub repo: String,
-    pub branch: Option<String>,
+/// Where a cached repo came from. Local sources have no hosting provider or ref.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    #[default]
+    Remote,
+    LocalDirectory,
+    LocalArchive,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,11 +96,35 @@ pub struct RepoInfo {
     pub key: String,
     pub owner: String,
     pub repo: String,
+    #[serde(default)]
+    pub source: SourceKind,
+    #[serde(default)]
+    pub provider: ProviderKind,
+    #[serde(default = "default_host")]
+    pub host: String,
+    /// Branch, tag or commit name the repo was imported from
     pub branch: String,
+    #[serde(default)]
+    pub ref_kind: RefKind,
+    #[serde(default)]
+    pub commit_sha: Option<String>,
+    /// Folder the import was limited to; the cached tree is rooted there
+    #[serde(default)]
+    pub subpath: Option<String>,
     pub imported_at: String,
     pub url: String,
     #[serde(default)]
     pub last_opened_at: Option<String>,
+    /// Kept when the cache is over its quota
+    #[serde(default)]
+    pub pinned: bool,
+    /// Gitignore-style patterns for this repo's tree, applied over every other rule
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore_patterns: Vec<String>,
+}
+
+fn default_host() -> String {
+    ProviderKind::GitHub.default_host().to_string()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -60,17 +134,87 @@ pub struct FileNode {
     pub is_dir: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub size: Option<u64>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_symlink: bool,
+    /// Repo-relative path a symlink points to; None if it leads outside the repo
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symlink_target: Option<String>,
+    /// SHA-256 of the file content, used to tell what changed between imports
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// Set on directories that are git submodules
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submodule: Option<SubmoduleInfo>,
+    /// Set on files that are Git LFS pointers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lfs_pointer: Option<LfsPointer>,
+    /// Detected language of a file; for a directory, the language most of its code is in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Lines in a text file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_count: Option<u64>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_binary: bool,
+    /// Set on files that are generated, vendored or minified
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub classification: Option<FileClass>,
+    /// Bytes in all files below a directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_size: Option<u64>,
+    /// Files below a directory, at any depth
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_count: Option<u64>,
+    /// Number of entries in a directory, also known when `children` isn't loaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub child_count: Option<usize>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub children: Option<Vec<FileNode>>,
 }
 
+/// Where a submodule comes from and whether its content was fetched
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmoduleInfo {
+    pub url: String,
+    /// Commit the parent repo pins, None if the provider couldn't tell
+    pub commit_sha: Option<String>,
+    /// False when the folder was left empty
+    pub populated: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImportResult {
     pub repo_key: String,
     pub info: RepoInfo,
+    /// The root node only; folders are listed on demand
     pub tree: FileNode,
 }
 
+/// Whether the ref a repo was imported from has moved since
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepoUpdateStatus {
+    pub repo_key: String,
+    pub current_sha: Option<String>,
+    pub latest_sha: Option<String>,
+    pub has_update: bool,
+}
+
+/// Repo-relative file paths that differ between two imports of a repo
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TreeChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefreshResult {
+    #[serde(flatten)]
+    pub import: ImportResult,
+    pub previous_sha: Option<String>,
+    pub changes: TreeChanges,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileContent {
     pub content: String,
@@ -78,11 +222,28 @@ pub struct FileContent {
     pub total_lines: Option<usize>,
     pub language: String,
     pub is_binary: bool,
+    /// Set when the file in the repo is a Git LFS pointer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lfs_pointer: Option<LfsPointer>,
+    /// True when `content` comes from the fetched LFS object rather than the pointer
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub lfs_fetched: bool,
 }
 
-#[derive(Debug, Deserialize)]
-struct GitHubRepoResponse {
-    default_branch: String,
+/// The object a Git LFS pointer file stands in for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LfsPointer {
+    /// SHA-256 of the real content, hex encoded
+    pub oid: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadProgress {
+    pub repo_key: String,
+    pub url: String,
+    pub received_bytes: u64,
+    pub total_bytes: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -95,26 +256,6 @@ pub struct SearchResultItem {
     pub repo: String,
 }
 
-#[derive(Debug, Deserialize)]
-struct GitHubSearchResponse {
-    items: Vec<GitHubSearchItem>,
-}
-
-#[derive(Debug, Deserialize)]
-struct GitHubSearchItem {
-    full_name: String,
-    description: Option<String>,
-    stargazers_count: u64,
-    html_url: String,
-    owner: GitHubOwner,
-    name: String,
-}
-
-#[derive(Debug, Deserialize)]
-struct GitHubOwner {
-    login: String,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TrendingRepo {
     pub full_name: String,
@@ -128,56 +269,6 @@ pub struct TrendingRepo {
     pub repo: String,
 }
 
-pub async fn search_github_repos(query: &str, token: Option<&str>) -> Result<Vec<SearchResultItem>, RepoError> {
-    if query.trim().is_empty() {
-        return Ok(vec![]);
-    }
-
-    let client = reqwest::Client::new();
-    let url = format!(
-        "https://api.github.com/search/repositories?q={}&per_page=15&sort=stars&order=desc",
-        urlencoding::encode(query)
-    );
-
-    let mut request = client
-        .get(&url)
-        .header("User-Agent", "RepoRead/0.1")
-        .header("Accept", "application/vnd.github.v3+json");
-
-    // Add token if provided
-    if let Some(t) = token {
-        if !t.is_empty() {
-            request = request.header("Authorization", format!("Bearer {}", t));
-        }
-    }
-
-    let response = request.send().await?;
-
-    if !response.status().is_success() {
-        return Err(RepoError::InvalidUrl(format!(
-            "GitHub API error: HTTP {}",
-            response.status()
-        )));
-    }
-
-    let search_response: GitHubSearchResponse = response.json().await?;
-
-    let results = search_response
-        .items
-        .into_iter()
-        .map(|item| SearchResultItem {
-            full_name: item.full_name,
-            description: item.description,
-            stargazers_count: item.stargazers_count,
-            html_url: item.html_url,
-            owner: item.owner.login,
-            repo: item.name,
-        })
-        .collect();
-
-    Ok(results)
-}
-
 fn parse_number(text: &str) -> Option<u64> {
     let digits: String = text
         .chars()
@@ -194,20 +285,17 @@ fn element_text(element: &scraper::ElementRef<'_>) -> String {
     element.text().collect::<Vec<_>>().join("").trim().to_string()
 }
 
+/// Scrapes the trending page of the GitHub instance at `web_base`
 pub async fn fetch_trending_repos(
+    web_base: &str,
     language: Option<&str>,
     since: &str,
     spoken_language: Option<&str>,
-) -> Result<Vec<TrendingRepo>, RepoError> {
-    let mut url = if let Some(lang) = language {
-        let lang = lang.trim();
-        if lang.is_empty() {
-            "https://github.com/trending".to_string()
-        } else {
-            format!("https://github.com/trending/{}", urlencoding::encode(lang))
-        }
-    } else {
-        "https://github.com/trending".to_string()
+) -> Result<Cached<Vec<TrendingRepo>>, RepoError> {
+    let web_base = web_base.trim_end_matches('/');
+    let mut url = match language.map(str::trim) {
+        Some(lang) if !lang.is_empty() => format!("{}/trending/{}", web_base, urlencoding::encode(lang)),
+        _ => format!("{}/trending", web_base),
     };
 
     let mut params = Vec::new();
@@ -225,23 +313,17 @@ pub async fn fetch_trending_repos(
         url.push_str(&params.join("&"));
     }
 
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&url)
-        .header("User-Agent", "RepoRead/0.1")
-        .header("Accept", "text/html")
-        .send()
-        .await?;
+    let request = http::client().get(&url).header("Accept", "text/html");
+    let response = http::get_cached(request).await?;
 
-    if !response.status().is_success() {
+    if !response.status.is_success() {
         return Err(RepoError::InvalidUrl(format!(
             "GitHub Trending error: HTTP {}",
-            response.status()
+            response.status
         )));
     }
 
-    let html = response.text().await?;
-    let document = Html::parse_document(&html);
+    let document = Html::parse_document(&response.body);
     let article_selector = Selector::parse("article.Box-row").unwrap();
     let title_selector = Selector::parse("h2 a").unwrap();
     let desc_selector = Selector::parse("p.col-9").unwrap();
@@ -272,7 +354,7 @@ pub async fn fetch_trending_repos(
             _ => continue,
         };
         let full_name = format!("{}/{}", owner, repo);
-        let url = format!("https://github.com/{}/{}", owner, repo);
+        let url = format!("{}/{}/{}", web_base, owner, repo);
 
         let description = article
             .select(&desc_selector)
@@ -314,15 +396,12 @@ pub async fn fetch_trending_repos(
         });
     }
 
-    Ok(results)
+    Ok(response.with(results))
 }
 
 // Settings management
 fn get_settings_path() -> PathBuf {
-    directories::ProjectDirs::from("com", "xnu", "RepoRead")
-        .map(|dirs| dirs.config_dir().to_path_buf())
-        .unwrap_or_else(|| PathBuf::from("./config"))
-        .join("settings.json")
+    paths::config_dir().join("settings.json")
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -335,6 +414,93 @@ pub struct AppSettings {
     pub interpret_prompt: String,
     #[serde(default = "default_model")]
     pub interpret_model: String,
+    #[serde(default = "default_max_extract_size_mb")]
+    pub max_extract_size_mb: u64,
+    #[serde(default = "default_max_extract_entries")]
+    pub max_extract_entries: u64,
+    /// Download pinned submodule archives into place during imports
+    #[serde(default)]
+    pub fetch_submodules: bool,
+    /// How many imports a batch runs at once
+    #[serde(default = "default_max_concurrent_imports")]
+    pub max_concurrent_imports: usize,
+    /// GitHub Enterprise Server instances, each with its own URLs and token
+    #[serde(default)]
+    pub github_hosts: Vec<GitHubHost>,
+    /// Proxy for every request, e.g. `http://proxy.example.com:8080`. Without one
+    /// the `HTTP_PROXY`/`HTTPS_PROXY` environment variables apply.
+    #[serde(default)]
+    pub proxy_url: Option<String>,
+    /// Comma-separated hosts that bypass `proxy_url`
+    #[serde(default)]
+    pub no_proxy: Option<String>,
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    /// Longest wait for the next bytes of a response; downloads may take longer in total
+    #[serde(default = "default_read_timeout_secs")]
+    pub read_timeout_secs: u64,
+    /// How downloaded repos are kept on disk
+    #[serde(default)]
+    pub storage_mode: StorageMode,
+    /// Disk space the repo cache may use before the least recently opened repos
+    /// are evicted. None for no limit.
+    #[serde(default)]
+    pub cache_quota_mb: Option<u64>,
+    /// Gitignore-style patterns hiding files from every repo's tree
+    #[serde(default = "default_ignore_patterns")]
+    pub ignore_patterns: Vec<String>,
+    /// Also hide what a repo's own `.gitignore` files list
+    #[serde(default = "default_true")]
+    pub respect_gitignore: bool,
+    /// Also hide files a repo's `.gitattributes` marks `linguist-vendored`
+    #[serde(default = "default_true")]
+    pub hide_vendored: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageMode {
+    /// Unpacked into a folder of files
+    #[default]
+    Extracted,
+    /// The downloaded zip is kept as is and files are read out of it on demand.
+    /// Applies to imports from a provider; local sources are always extracted.
+    Archive,
+    /// Unpacked, then each file's contents are kept once in a blob store shared
+    /// by every repo and ref, so unchanged files across refs cost no extra space
+    Deduplicated,
+}
+
+/// A GitHub instance other than github.com. Unset URLs fall back to the
+/// GitHub Enterprise Server defaults for `host`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GitHubHost {
+    /// Host name as it appears in repository URLs, e.g. `github.example.com`
+    pub host: String,
+    /// Defaults to `https://<host>`
+    #[serde(default)]
+    pub web_url: Option<String>,
+    /// Defaults to `https://<host>/api/v3`
+    #[serde(default)]
+    pub api_url: Option<String>,
+    /// codeload-style archive server; without one, archives come from the API's zipball endpoint
+    #[serde(default)]
+    pub archive_url: Option<String>,
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+impl AppSettings {
+    /// The configured GitHub instance serving `host`, matched by host name or web URL
+    pub fn github_host(&self, host: &str) -> Option<&GitHubHost> {
+        self.github_hosts.iter().find(|h| {
+            h.host.eq_ignore_ascii_case(host)
+                || h.web_url.as_deref().is_some_and(|url| {
+                    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+                    rest.split('/').next().unwrap_or(rest).eq_ignore_ascii_case(host)
+                })
+        })
+    }
 }
 
 fn default_true() -> bool {
@@ -349,6 +515,33 @@ fn default_model() -> String {
     "anthropic/claude-sonnet-4".to_string()
 }
 
+fn default_max_extract_size_mb() -> u64 {
+    8 * 1024
+}
+
+fn default_max_extract_entries() -> u64 {
+    1_000_000
+}
+
+fn default_max_concurrent_imports() -> usize {
+    3
+}
+
+fn default_ignore_patterns() -> Vec<String> {
+    [".git/", "node_modules/", "__pycache__/", ".DS_Store"]
+        .iter()
+        .map(|p| p.to_string())
+        .collect()
+}
+
+fn default_connect_timeout_secs() -> u64 {
+    15
+}
+
+fn default_read_timeout_secs() -> u64 {
+    60
+}
+
 impl Default for AppSettings {
     fn default() -> Self {
         Self {
@@ -357,6 +550,20 @@ impl Default for AppSettings {
             openrouter_api_key: None,
             interpret_prompt: default_interpret_prompt(),
             interpret_model: default_model(),
+            max_extract_size_mb: default_max_extract_size_mb(),
+            max_extract_entries: default_max_extract_entries(),
+            fetch_submodules: false,
+            max_concurrent_imports: default_max_concurrent_imports(),
+            github_hosts: Vec::new(),
+            proxy_url: None,
+            no_proxy: None,
+            connect_timeout_secs: default_connect_timeout_secs(),
+            read_timeout_secs: default_read_timeout_secs(),
+            storage_mode: StorageMode::default(),
+            cache_quota_mb: None,
+            ignore_patterns: default_ignore_patterns(),
+            respect_gitignore: true,
+            hide_vendored: true,
         }
     }
 }
@@ -381,10 +588,7 @@ pub fn save_settings(settings: &AppSettings) -> Result<(), RepoError> {
 }
 
 fn get_favorites_path() -> PathBuf {
-    directories::ProjectDirs::from("com", "xnu", "RepoRead")
-        .map(|dirs| dirs.config_dir().to_path_buf())
-        .unwrap_or_else(|| PathBuf::from("./config"))
-        .join("favorites.json")
+    paths::config_dir().join("favorites.json")
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -483,10 +687,7 @@ struct AllFileHistory {
 }
 
 fn get_file_history_path() -> PathBuf {
-    directories::ProjectDirs::from("com", "xnu", "RepoRead")
-        .map(|dirs| dirs.config_dir().to_path_buf())
-        .unwrap_or_else(|| PathBuf::from("./config"))
-        .join("file_history.json")
+    paths::config_dir().join("file_history.json")
 }
 
 fn load_all_file_history() -> AllFileHistory {
@@ -546,213 +747,1382 @@ pub fn add_file_history(repo_url: &str, file_path: &str) -> Result<(), RepoError
     save_all_file_history(&all)
 }
 
-pub fn parse_github_url(url: &str) -> Result<ParsedGitHubUrl, RepoError> {
-    let url = url.trim().trim_end_matches('/');
+// In-flight imports, keyed by repo key, so they can be cancelled from the UI
+fn active_imports() -> &'static Mutex<HashMap<String, Arc<AtomicBool>>> {
+    static ACTIVE_IMPORTS: OnceLock<Mutex<HashMap<String, Arc<AtomicBool>>>> = OnceLock::new();
+    ACTIVE_IMPORTS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+pub fn begin_import(repo_key: &str) -> Arc<AtomicBool> {
+    let flag = Arc::new(AtomicBool::new(false));
+    active_imports()
+        .lock()
+        .unwrap()
+        .insert(repo_key.to_string(), flag.clone());
+    flag
+}
+
+pub fn end_import(repo_key: &str) {
+    active_imports().lock().unwrap().remove(repo_key);
+}
 
-    // Remove protocol
-    let path = url
-        .strip_prefix("https://github.com/")
-        .or_else(|| url.strip_prefix("http://github.com/"))
-        .or_else(|| url.strip_prefix("github.com/"))
-        .ok_or_else(|| RepoError::InvalidUrl("Not a GitHub URL".into()))?;
+pub fn imports_running() -> bool {
+    !active_imports().lock().unwrap().is_empty()
+}
 
-    let parts: Vec<&str> = path.split('/').collect();
+pub fn import_running(repo_key: &str) -> bool {
+    active_imports().lock().unwrap().contains_key(repo_key)
+}
 
-    if parts.len() < 2 {
-        return Err(RepoError::InvalidUrl("Missing owner or repo".into()));
+/// Signals an in-flight import to stop. Returns false if no import is running for the key.
+pub fn cancel_import(repo_key: &str) -> bool {
+    match active_imports().lock().unwrap().get(repo_key) {
+        Some(flag) => {
+            flag.store(true, Ordering::SeqCst);
+            true
+        }
+        None => false,
     }
+}
 
-    let owner = parts[0].to_string();
-    let repo = parts[1].to_string();
+/// Caps applied while unpacking third-party archives, so a hostile or broken
+/// archive can't fill the disk
+#[derive(Debug, Clone, Copy)]
+pub struct ExtractLimits {
+    pub max_total_bytes: u64,
+    pub max_entries: usize,
+}
 
-    // Check for /tree/<branch> pattern
-    let branch = if parts.len() >= 4 && parts[2] == "tree" {
-        Some(parts[3..].join("/"))
-    } else {
-        None
-    };
+impl ExtractLimits {
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        Self {
+            max_total_bytes: settings.max_extract_size_mb.saturating_mul(1024 * 1024),
+            max_entries: settings.max_extract_entries as usize,
+        }
+    }
+}
 
-    Ok(ParsedGitHubUrl { owner, repo, branch })
+/// Tracks bytes and entries written against the limits
+struct ExtractBudget {
+    limits: ExtractLimits,
+    written_bytes: u64,
+    entries: usize,
 }
 
-pub async fn get_default_branch(owner: &str, repo: &str) -> Result<String, RepoError> {
-    let client = reqwest::Client::new();
-    let url = format!("https://api.github.com/repos/{}/{}", owner, repo);
+impl ExtractBudget {
+    fn new(limits: ExtractLimits) -> Self {
+        Self {
+            limits,
+            written_bytes: 0,
+            entries: 0,
+        }
+    }
 
-    let response = client
-        .get(&url)
-        .header("User-Agent", "RepoRead/0.1")
-        .header("Accept", "application/vnd.github.v3+json")
-        .send()
-        .await?;
+    fn add_entry(&mut self) -> Result<(), RepoError> {
+        self.entries += 1;
+        if self.entries > self.limits.max_entries {
+            return Err(RepoError::UnsafeArchive(format!(
+                "more than {} entries",
+                self.limits.max_entries
+            )));
+        }
+        Ok(())
+    }
 
-    if !response.status().is_success() {
-        // Fallback to "main" if API fails
-        return Ok("main".to_string());
+    /// Copies at most the remaining byte budget; declared sizes in headers can't be trusted
+    fn copy(&mut self, reader: &mut impl Read, writer: &mut impl io::Write) -> Result<(), RepoError> {
+        let remaining = self.limits.max_total_bytes.saturating_sub(self.written_bytes);
+        let copied = io::copy(&mut reader.take(remaining.saturating_add(1)), writer)?;
+        self.written_bytes += copied;
+        if self.written_bytes > self.limits.max_total_bytes {
+            return Err(RepoError::UnsafeArchive(format!(
+                "uncompressed size exceeds {} MB",
+                self.limits.max_total_bytes / (1024 * 1024)
+            )));
+        }
+        Ok(())
     }
+}
+
+/// Repo files live in this folder of the repo dir, next to `_meta`, so no
+/// repo content can clash with the metadata
+const CONTENT_DIR: &str = "files";
 
-    let repo_info: GitHubRepoResponse = response.json().await?;
-    Ok(repo_info.default_branch)
+pub fn content_dir(repo_dir: &Path) -> PathBuf {
+    repo_dir.join(CONTENT_DIR)
 }
 
-pub async fn download_repo_zip(
-    owner: &str,
-    repo: &str,
-    branch: &str,
-    dest_path: &Path,
-) -> Result<(), RepoError> {
-    let zip_url = format!(
-        "https://codeload.github.com/{}/{}/zip/refs/heads/{}",
-        owner, repo, branch
-    );
+/// Symlinks in a repo, keyed by repo-relative path, with their raw target.
+/// They are stored as small text files holding the target (as git does where
+/// symlinks are unsupported) and listed in `_meta/symlinks.json`.
+type SymlinkMap = std::collections::BTreeMap<String, String>;
 
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&zip_url)
-        .header("User-Agent", "RepoRead/0.1")
-        .send()
-        .await?;
+/// Rejects absolute paths, drive prefixes and `..` components. Names are
+/// split on `\\` as well as `/`, so an archive is judged the same whatever
+/// platform extracts it.
+fn safe_relative_path(name: &str) -> Result<String, RepoError> {
+    let escapes = || RepoError::UnsafeArchive(format!("entry escapes the destination: {}", name));
+    if name.starts_with(['/', '\\']) {
+        return Err(escapes());
+    }
 
-    if !response.status().is_success() {
-        return Err(RepoError::InvalidUrl(format!(
-            "Failed to download: HTTP {}",
-            response.status()
-        )));
+    let mut parts = Vec::new();
+    for (i, part) in name.split(['/', '\\']).enumerate() {
+        match part {
+            "" | "." => {}
+            ".." => return Err(escapes()),
+            // `C:` and other drive prefixes
+            part if i == 0 && part.contains(':') => return Err(escapes()),
+            part => parts.push(part),
+        }
     }
+    Ok(parts.join("/"))
+}
 
-    let bytes = response.bytes().await?;
+/// Resolves a symlink target against the link's folder. Returns None when it
+/// would point outside the repo.
+fn resolve_symlink_target(link_path: &str, target: &str) -> Option<String> {
+    if target.starts_with('/') || target.starts_with('\\') {
+        return None;
+    }
 
-    // Create parent directory
-    if let Some(parent) = dest_path.parent() {
-        fs::create_dir_all(parent)?;
+    let mut parts: Vec<&str> = link_path.split('/').collect();
+    parts.pop();
+    for segment in target.split(['/', '\\']) {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            other => parts.push(other),
+        }
     }
+    Some(parts.join("/"))
+}
 
-    let mut file = File::create(dest_path)?;
-    file.write_all(&bytes)?;
+fn write_symlink_placeholder(
+    dest_dir: &Path,
+    relative_path: &str,
+    target: &str,
+    symlinks: &mut SymlinkMap,
+) -> Result<(), RepoError> {
+    let out_path = dest_dir.join(relative_path);
+    if let Some(parent) = out_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&out_path, target)?;
+    symlinks.insert(relative_path.to_string(), target.to_string());
+    Ok(())
+}
 
+fn save_symlinks(repo_dir: &Path, symlinks: &SymlinkMap) -> Result<(), RepoError> {
+    if symlinks.is_empty() {
+        return Ok(());
+    }
+    let meta_dir = repo_dir.join("_meta");
+    fs::create_dir_all(&meta_dir)?;
+    let json = serde_json::to_string_pretty(symlinks)?;
+    fs::write(meta_dir.join("symlinks.json"), json)?;
     Ok(())
 }
 
-pub fn extract_zip(zip_path: &Path, dest_dir: &Path) -> Result<String, RepoError> {
-    let file = File::open(zip_path)?;
-    let mut archive = zip::ZipArchive::new(file)?;
+fn load_symlinks(repo_dir: &Path) -> SymlinkMap {
+    fs::read_to_string(repo_dir.join("_meta").join("symlinks.json"))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
 
-    // Get root folder name (GitHub adds repo-branch prefix)
-    let root_name = archive
-        .by_index(0)?
-        .name()
-        .split('/')
-        .next()
-        .unwrap_or("")
-        .to_string();
+/// Submodules keyed by repo-relative path, stored in `_meta/submodules.json`
+pub type SubmoduleMap = std::collections::BTreeMap<String, SubmoduleInfo>;
 
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i)?;
-        let name = file.name().to_string();
+/// A `[submodule]` section of `.gitmodules`
+#[derive(Debug, Clone)]
+pub struct GitSubmodule {
+    pub path: String,
+    pub url: String,
+}
 
-        // Skip the root folder prefix
-        let relative_path = name
-            .strip_prefix(&format!("{}/", root_name))
-            .unwrap_or(&name);
+/// Reads the `path` and `url` of each submodule. Sections missing either, or
+/// whose path would leave the repo, are dropped.
+pub fn parse_gitmodules(text: &str) -> Vec<GitSubmodule> {
+    let mut submodules = Vec::new();
+    let mut current: Option<(Option<String>, Option<String>)> = None;
 
-        if relative_path.is_empty() {
-            continue;
+    let mut flush = |section: Option<(Option<String>, Option<String>)>| {
+        if let Some((Some(path), Some(url))) = section {
+            if let Ok(path) = safe_relative_path(&path) {
+                if !path.is_empty() {
+                    submodules.push(GitSubmodule { path, url });
+                }
+            }
         }
+    };
 
-        let out_path = dest_dir.join(relative_path);
-
-        if file.is_dir() {
-            fs::create_dir_all(&out_path)?;
-        } else {
-            if let Some(parent) = out_path.parent() {
-                fs::create_dir_all(parent)?;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if line.starts_with('[') {
+            flush(current.take());
+            if line.starts_with("[submodule") {
+                current = Some((None, None));
             }
-            let mut outfile = File::create(&out_path)?;
-            io::copy(&mut file, &mut outfile)?;
+            continue;
+        }
+        let (Some(section), Some((key, value))) = (current.as_mut(), line.split_once('=')) else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"').to_string();
+        match key.trim() {
+            "path" => section.0 = Some(value),
+            "url" => section.1 = Some(value),
+            _ => {}
         }
     }
+    flush(current);
 
-    // Remove ZIP file after extraction
-    fs::remove_file(zip_path)?;
+    submodules
+}
 
-    Ok(root_name)
+pub fn save_submodules(repo_dir: &Path, submodules: &SubmoduleMap) -> Result<(), RepoError> {
+    if submodules.is_empty() {
+        return Ok(());
+    }
+    let meta_dir = repo_dir.join("_meta");
+    fs::create_dir_all(&meta_dir)?;
+    let json = serde_json::to_string_pretty(submodules)?;
+    fs::write(meta_dir.join("submodules.json"), json)?;
+    Ok(())
 }
 
-pub fn build_file_tree(root_path: &Path, base_name: &str) -> Result<FileNode, RepoError> {
-    fn build_node(path: &Path, root: &Path) -> Option<FileNode> {
-        let name = path.file_name()?.to_string_lossy().to_string();
+fn load_submodules(repo_dir: &Path) -> SubmoduleMap {
+    fs::read_to_string(repo_dir.join("_meta").join("submodules.json"))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Reads one small text file from a downloaded archive without extracting it.
+/// `relative_path` is relative to the repo root, i.e. below the archive's root folder.
+pub fn read_zip_text(zip_path: &Path, relative_path: &str) -> Result<Option<String>, RepoError> {
+    const MAX_TEXT_BYTES: u64 = 1024 * 1024;
+
+    let mut archive = zip::ZipArchive::new(File::open(zip_path)?)?;
+    let names: Vec<String> = archive.file_names().map(|n| n.to_string()).collect();
+    let root_name = common_root(names.iter().map(|n| n.as_str()));
 
-        // Skip hidden files and common unneeded dirs
-        if name.starts_with('.') || name == "node_modules" || name == "__pycache__" || name == "_meta" {
+    let entry_name = names
+        .iter()
+        .find(|name| select_entry(name, root_name.as_deref(), None) == Some(relative_path));
+    let Some(entry_name) = entry_name else {
+        return Ok(None);
+    };
+
+    let mut text = String::new();
+    archive
+        .by_name(entry_name)?
+        .take(MAX_TEXT_BYTES)
+        .read_to_string(&mut text)?;
+    Ok(Some(text))
+}
+
+/// Returns the single top-level folder shared by every entry, if there is one.
+/// Hosted archives always wrap the repo in one (e.g. `repo-main/`), local ones may not.
+/// The folder's own entry, which names arrive without a trailing `/`, counts as inside it.
+fn common_root<'a>(names: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    let mut root: Option<&str> = None;
+    let mut nested = false;
+    for name in names {
+        let first = match name.split_once('/') {
+            Some((first, _)) => {
+                nested = true;
+                first
+            }
+            None => name,
+        };
+        if first.is_empty() {
             return None;
         }
+        match root {
+            None => root = Some(first),
+            Some(r) if r != first => return None,
+            _ => {}
+        }
+    }
+    // A lone top-level entry is a file, not a folder to strip
+    root.filter(|_| nested).map(|r| r.to_string())
+}
+
+/// Maps an archive entry to its path inside the repo: strips the shared root
+/// folder and, for subfolder imports, anything outside `subpath`.
+fn select_entry<'a>(name: &'a str, root: Option<&str>, subpath: Option<&str>) -> Option<&'a str> {
+    let relative_path = match root {
+        Some(root) => name
+            .strip_prefix(root)
+            .and_then(|rest| if rest.is_empty() { Some(rest) } else { rest.strip_prefix('/') })
+            .unwrap_or(name),
+        None => name,
+    };
 
-        let relative_path = path.strip_prefix(root).ok()?.to_string_lossy().to_string();
+    let relative_path = match subpath {
+        Some(prefix) => match relative_path.strip_prefix(prefix) {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => rest.trim_start_matches('/'),
+            _ => return None,
+        },
+        None => relative_path,
+    };
 
-        if path.is_dir() {
-            let mut children: Vec<FileNode> = fs::read_dir(path)
-                .ok()?
-                .filter_map(|e| e.ok())
-                .filter_map(|e| build_node(&e.path(), root))
-                .collect();
+    (!relative_path.is_empty()).then_some(relative_path)
+}
 
-            // Sort: directories first, then by name
-            children.sort_by(|a, b| {
-                match (a.is_dir, b.is_dir) {
-                    (true, false) => std::cmp::Ordering::Less,
-                    (false, true) => std::cmp::Ordering::Greater,
-                    _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-                }
-            });
+/// Checks every entry name and the declared sizes against the limits before
+/// anything is written, and returns the entry names in archive order
+fn validate_zip_entries(archive: &mut zip::ZipArchive<File>, limits: &ExtractLimits) -> Result<Vec<String>, RepoError> {
+    if archive.len() > limits.max_entries {
+        return Err(RepoError::UnsafeArchive(format!(
+            "{} entries exceeds the limit of {}",
+            archive.len(),
+            limits.max_entries
+        )));
+    }
 
-            Some(FileNode {
-                name,
-                path: relative_path,
-                is_dir: true,
-                size: None,
-                children: Some(children),
-            })
-        } else {
-            let size = fs::metadata(path).ok()?.len();
-            Some(FileNode {
-                name,
-                path: relative_path,
-                is_dir: false,
-                size: Some(size),
-                children: None,
-            })
+    let mut names = Vec::with_capacity(archive.len());
+    let mut declared_size: u64 = 0;
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i)?;
+        if file.enclosed_name().is_none() {
+            return Err(RepoError::UnsafeArchive(format!(
+                "entry escapes the destination: {}",
+                file.name()
+            )));
         }
+        names.push(safe_relative_path(file.name())?);
+        declared_size = declared_size.saturating_add(file.size());
+    }
+    if declared_size > limits.max_total_bytes {
+        return Err(RepoError::UnsafeArchive(format!(
+            "uncompressed size exceeds {} MB",
+            limits.max_total_bytes / (1024 * 1024)
+        )));
     }
 
-    build_node(root_path, root_path)
-        .map(|mut node| {
-            node.name = base_name.to_string();
-            node.path = "".to_string();
-            node
-        })
-        .ok_or_else(|| RepoError::IoError(io::Error::new(io::ErrorKind::NotFound, "Root not found")))
+    Ok(names)
 }
 
-pub fn detect_language(file_path: &str) -> String {
-    let ext = Path::new(file_path)
-        .extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("");
+/// Extracts the archive into the content folder of `repo_dir`. With `subpath`,
+/// only entries under that folder are written, and they land at the top.
+pub fn extract_zip(
+    zip_path: &Path,
+    repo_dir: &Path,
+    subpath: Option<&str>,
+    limits: &ExtractLimits,
+) -> Result<String, RepoError> {
+    unpack_zip(zip_path, repo_dir, subpath, "", limits)
+}
 
-    match ext.to_lowercase().as_str() {
-        "js" | "mjs" | "cjs" => "javascript",
-        "jsx" => "javascript",
-        "ts" | "mts" | "cts" => "typescript",
-        "tsx" => "typescript",
-        "py" | "pyw" => "python",
-        "json" => "json",
-        "md" | "markdown" => "markdown",
-        "html" | "htm" => "html",
-        "css" => "css",
-        "scss" | "sass" => "scss",
-        "yaml" | "yml" => "yaml",
-        "toml" => "toml",
-        "rs" => "rust",
+/// Extracts a submodule's archive into its folder in `repo_dir`'s content
+pub fn extract_submodule(
+    zip_path: &Path,
+    repo_dir: &Path,
+    submodule_path: &str,
+    limits: &ExtractLimits,
+) -> Result<(), RepoError> {
+    unpack_zip(zip_path, repo_dir, None, submodule_path, limits).map(|_| ())
+}
+
+/// Extracts the selected entries to `into` within the content folder. Symlinks
+/// are added to those already recorded for the repo.
+fn unpack_zip(
+    zip_path: &Path,
+    repo_dir: &Path,
+    subpath: Option<&str>,
+    into: &str,
+    limits: &ExtractLimits,
+) -> Result<String, RepoError> {
+    let dest_dir = content_dir(repo_dir);
+    let file = File::open(zip_path)?;
+    let mut archive = zip::ZipArchive::new(file)?;
+    let names = validate_zip_entries(&mut archive, limits)?;
+
+    // Get root folder name (GitHub adds repo-ref prefix)
+    let root_name = common_root(names.iter().map(|n| n.as_str()));
+    let mut budget = ExtractBudget::new(*limits);
+    let mut symlinks = load_symlinks(repo_dir);
+    let mut extracted = 0usize;
+    fs::create_dir_all(dest_dir.join(into))?;
+
+    for (i, name) in names.iter().enumerate() {
+        let relative_path = match select_entry(name, root_name.as_deref(), subpath) {
+            Some("") if !into.is_empty() => into.to_string(),
+            Some(path) if !into.is_empty() => format!("{}/{}", into, path),
+            Some(path) => path.to_string(),
+            None => continue,
+        };
+        let relative_path = relative_path.as_str();
+        extracted += 1;
+        budget.add_entry()?;
+
+        let mut file = archive.by_index(i)?;
+        let out_path = dest_dir.join(relative_path);
+
+        if file.is_dir() {
+            fs::create_dir_all(&out_path)?;
+        } else if file.is_symlink() {
+            let mut target = String::new();
+            (&mut file).take(4096).read_to_string(&mut target)?;
+            write_symlink_placeholder(&dest_dir, relative_path, &target, &mut symlinks)?;
+        } else {
+            if let Some(parent) = out_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut outfile = File::create(&out_path)?;
+            budget.copy(&mut file, &mut outfile)?;
+        }
+    }
+
+    if let Some(prefix) = subpath {
+        if extracted == 0 {
+            return Err(RepoError::RepoNotFound(format!("Path not found in repository: {}", prefix)));
+        }
+    }
+
+    save_symlinks(repo_dir, &symlinks)?;
+
+    Ok(root_name.unwrap_or_default())
+}
+
+/// Repos not kept as a plain folder list their files in `_meta/index.json`.
+/// Archive-backed repos keep the download beside it; deduplicated repos keep
+/// file contents in the shared blob store.
+const ARCHIVE_FILE: &str = "archive.zip";
+const CONTENT_INDEX_FILE: &str = "index.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum EntryCompression {
+    Stored,
+    Deflated,
+    /// Anything else is read through the zip crate
+    Other,
+}
+
+/// Where a file's bytes sit in the archive, taken from the central directory
+/// so reads can seek straight to them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ArchiveLocation {
+    index: usize,
+    data_start: u64,
+    compressed_size: u64,
+    compression: EntryCompression,
+}
+
+/// One file of an indexed repo, with what the tree needs to know about it.
+/// Files outside an archive are read from the blob store by hash.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedFile {
+    size: u64,
+    hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lfs_pointer: Option<LfsPointer>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    archive: Option<ArchiveLocation>,
+    /// Missing from indexes written before stats were gathered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stats: Option<ContentStats>,
+}
+
+/// Contents of an indexed repo, keyed by repo-relative path
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ContentIndex {
+    files: std::collections::BTreeMap<String, IndexedFile>,
+    /// Folders listed explicitly; others are implied by file paths
+    dirs: std::collections::BTreeSet<String>,
+    /// Length of the kept archive, to tell it from the archive of a copy that
+    /// replaced this one. Missing from indexes written before it was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    archive_size: Option<u64>,
+}
+
+fn archive_file_path(repo_dir: &Path) -> PathBuf {
+    repo_dir.join("_meta").join(ARCHIVE_FILE)
+}
+
+fn content_index_path(repo_dir: &Path) -> PathBuf {
+    repo_dir.join("_meta").join(CONTENT_INDEX_FILE)
+}
+
+/// Parsed indexes by repo dir
+type IndexCache = HashMap<PathBuf, Arc<ContentIndex>>;
+
+/// Indexes are only ever written into a staged repo, so a cached one goes
+/// stale only when its repo dir is swapped or deleted, which forgets it.
+/// Timestamps can't tell, since coarse filesystems give both copies the same.
+fn content_indexes() -> &'static Mutex<IndexCache> {
+    static INDEXES: OnceLock<Mutex<IndexCache>> = OnceLock::new();
+    INDEXES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// The index of an archive-backed or deduplicated repo; None for extracted repos
+fn load_content_index(repo_dir: &Path) -> Option<Arc<ContentIndex>> {
+    if let Some(index) = content_indexes().lock().unwrap().get(repo_dir) {
+        return Some(index.clone());
+    }
+    let path = content_index_path(repo_dir);
+    let index: Arc<ContentIndex> = Arc::new(serde_json::from_str(&fs::read_to_string(&path).ok()?).ok()?);
+    content_indexes()
+        .lock()
+        .unwrap()
+        .insert(repo_dir.to_path_buf(), index.clone());
+    Some(index)
+}
+
+fn forget_content_index(repo_dir: &Path) {
+    content_indexes().lock().unwrap().remove(repo_dir);
+}
+
+/// File contents shared by every deduplicated repo, named by their SHA-256
+/// and fanned out by the first two hex digits
+fn get_blobs_dir() -> PathBuf {
+    get_repos_dir().join(".blobs")
+}
+
+fn blob_path(hash: &str) -> PathBuf {
+    get_blobs_dir().join(&hash[..2]).join(hash)
+}
+
+/// Hashes of blobs moved into the store for an index not yet written, with
+/// how many imports are storing each
+type PendingBlobs = HashMap<String, usize>;
+
+/// Held while a blob is moved in, while blobs are collected, and while staged
+/// repos are swapped into place, so a collection never deletes a blob an
+/// index refers to or is about to
+fn blob_store_lock() -> &'static Mutex<PendingBlobs> {
+    static LOCK: OnceLock<Mutex<PendingBlobs>> = OnceLock::new();
+    LOCK.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Blobs one `store_blobs` call has moved in, kept from collection until dropped
+#[derive(Default)]
+struct BlobClaims(Vec<String>);
+
+impl BlobClaims {
+    /// Moves the file at `path` into the store as `hash`, or drops it when the
+    /// store already has that content
+    fn store(&mut self, path: &Path, hash: &str) -> Result<(), RepoError> {
+        let blob = blob_path(hash);
+        let mut pending = blob_store_lock().lock().unwrap();
+        if blob.exists() {
+            fs::remove_file(path)?;
+        } else {
+            if let Some(parent) = blob.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::rename(path, &blob)?;
+        }
+        *pending.entry(hash.to_string()).or_default() += 1;
+        self.0.push(hash.to_string());
+        Ok(())
+    }
+}
+
+impl Drop for BlobClaims {
+    fn drop(&mut self) {
+        let mut pending = blob_store_lock().lock().unwrap_or_else(|e| e.into_inner());
+        for hash in &self.0 {
+            if let Some(count) = pending.get_mut(hash) {
+                *count -= 1;
+                if *count == 0 {
+                    pending.remove(hash);
+                }
+            }
+        }
+    }
+}
+
+/// Moves the files of an extracted repo into the blob store and indexes them
+/// in place of its content folder. Contents another repo or ref already
+/// stored are dropped rather than stored twice. Files are hashed without the
+/// store lock, which is only taken for each move.
+pub fn store_blobs(dir: &Path) -> Result<(), RepoError> {
+    fn ingest(dir: &Path, prefix: &str, index: &mut ContentIndex, claims: &mut BlobClaims) -> Result<(), RepoError> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let relative_path = if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) };
+
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                ingest(&path, &relative_path, index, claims)?;
+                index.dirs.insert(relative_path);
+            } else if file_type.is_file() {
+                let (hash, stats) = stats::scan_file(&path)
+                    .ok_or_else(|| RepoError::IoError(io::Error::other(format!("Could not read {}", relative_path))))?;
+                let file = IndexedFile {
+                    size: entry.metadata()?.len(),
+                    lfs_pointer: read_lfs_pointer(&path),
+                    hash,
+                    archive: None,
+                    stats: Some(stats),
+                };
+
+                claims.store(&path, &file.hash)?;
+                index.files.insert(relative_path, file);
+            }
+        }
+        Ok(())
+    }
+
+    // Claims are released once the index referring to the blobs is written
+    let mut claims = BlobClaims::default();
+    let mut index = ContentIndex::default();
+    let content = content_dir(dir);
+    if content.is_dir() {
+        ingest(&content, "", &mut index, &mut claims)?;
+    }
+
+    fs::create_dir_all(dir.join("_meta"))?;
+    fs::write(content_index_path(dir), serde_json::to_string(&index)?)?;
+    if content.exists() {
+        fs::remove_dir_all(&content)?;
+    }
+    Ok(())
+}
+
+/// Hashes of the blobs stored repos refer to, including repos still being
+/// staged and copies set aside during a swap
+fn referenced_blobs() -> Result<std::collections::HashSet<String>, RepoError> {
+    let mut referenced = std::collections::HashSet::new();
+    for parent in [get_repos_dir(), get_staging_dir()] {
+        let entries = match fs::read_dir(&parent) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
+            let path = entry?.path();
+            if !path.is_dir() {
+                continue;
+            }
+            let json = match fs::read_to_string(content_index_path(&path)) {
+                Ok(json) => json,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            let index: ContentIndex = serde_json::from_str(&json)?;
+            referenced.extend(
+                index
+                    .files
+                    .into_values()
+                    .filter(|file| file.archive.is_none())
+                    .map(|file| file.hash),
+            );
+        }
+    }
+    Ok(referenced)
+}
+
+/// Deletes blobs no repo refers to any more. Nothing is deleted unless every
+/// index could be read, so an unreadable repo never loses its contents.
+pub fn collect_blobs() {
+    let blobs_dir = get_blobs_dir();
+    if !blobs_dir.is_dir() {
+        return;
+    }
+
+    let pending = blob_store_lock().lock().unwrap();
+    let Ok(mut referenced) = referenced_blobs() else {
+        return;
+    };
+    referenced.extend(pending.keys().cloned());
+    let Ok(shards) = fs::read_dir(&blobs_dir) else {
+        return;
+    };
+    for shard in shards.filter_map(|e| e.ok()) {
+        let Ok(blobs) = fs::read_dir(shard.path()) else {
+            continue;
+        };
+        for blob in blobs.filter_map(|e| e.ok()) {
+            if !referenced.contains(blob.file_name().to_string_lossy().as_ref()) {
+                let _ = fs::remove_file(blob.path());
+            }
+        }
+        // Only succeeds once the shard is empty
+        let _ = fs::remove_dir(shard.path());
+    }
+}
+
+/// Moves the downloaded zip into `dest_dir/_meta` and indexes it instead of
+/// extracting. Entries are selected and limited as in `extract_zip`, and each
+/// file is read once to hash it, gather its stats and spot LFS pointers.
+pub fn store_zip(
+    zip_path: &Path,
+    dest_dir: &Path,
+    subpath: Option<&str>,
+    limits: &ExtractLimits,
+) -> Result<String, RepoError> {
+    let meta_dir = dest_dir.join("_meta");
+    fs::create_dir_all(&meta_dir)?;
+    let stored_path = archive_file_path(dest_dir);
+    fs::rename(zip_path, &stored_path)?;
+
+    let mut archive = zip::ZipArchive::new(File::open(&stored_path)?)?;
+    let names = validate_zip_entries(&mut archive, limits)?;
+    let root_name = common_root(names.iter().map(|n| n.as_str()));
+
+    let mut budget = ExtractBudget::new(*limits);
+    let mut symlinks = SymlinkMap::new();
+    let mut index = ContentIndex::default();
+
+    for (i, name) in names.iter().enumerate() {
+        let relative_path = match select_entry(name, root_name.as_deref(), subpath) {
+            Some(path) => path,
+            None => continue,
+        };
+        budget.add_entry()?;
+
+        let mut file = archive.by_index(i)?;
+        if file.is_dir() {
+            index.dirs.insert(relative_path.to_string());
+            continue;
+        }
+
+        let compression = match file.compression() {
+            zip::CompressionMethod::Stored => EntryCompression::Stored,
+            zip::CompressionMethod::Deflated => EntryCompression::Deflated,
+            _ => EntryCompression::Other,
+        };
+        let (size, data_start, compressed_size) = (file.size(), file.data_start(), file.compressed_size());
+
+        // Small files are kept in memory to check for LFS pointers and symlink targets
+        let mut scanner = FileScanner::default();
+        let mut lfs_pointer = None;
+        if size <= MAX_LFS_POINTER_SIZE || file.is_symlink() {
+            let mut data = Vec::new();
+            budget.copy(&mut file, &mut data)?;
+            io::Write::write_all(&mut scanner, &data)?;
+            if file.is_symlink() {
+                symlinks.insert(relative_path.to_string(), String::from_utf8_lossy(&data).into_owned());
+            } else {
+                lfs_pointer = parse_lfs_pointer(&data);
+            }
+        } else {
+            budget.copy(&mut file, &mut scanner)?;
+        }
+
+        let (hash, stats) = scanner.finish();
+        index.files.insert(
+            relative_path.to_string(),
+            IndexedFile {
+                size,
+                hash,
+                stats: Some(stats),
+                lfs_pointer,
+                archive: Some(ArchiveLocation {
+                    index: i,
+                    data_start,
+                    compressed_size,
+                    compression,
+                }),
+            },
+        );
+    }
+
+    if let Some(prefix) = subpath {
+        if index.files.is_empty() && index.dirs.is_empty() {
+            return Err(RepoError::RepoNotFound(format!("Path not found in repository: {}", prefix)));
+        }
+    }
+
+    index.archive_size = Some(fs::metadata(&stored_path)?.len());
+    fs::write(content_index_path(dest_dir), serde_json::to_string(&index)?)?;
+    save_symlinks(dest_dir, &symlinks)?;
+
+    Ok(root_name.unwrap_or_default())
+}
+
+/// Opens a reader over one file of an indexed repo: its blob, or its entry in
+/// the archive. Stored and deflated entries are read straight from their
+/// offset without parsing the whole zip. None if the archive isn't the one
+/// `index` was built from.
+fn open_indexed_file(
+    repo_dir: &Path,
+    index: &ContentIndex,
+    entry: &IndexedFile,
+) -> Result<Option<Box<dyn Read>>, RepoError> {
+    use std::io::{Seek, SeekFrom};
+
+    let Some(location) = &entry.archive else {
+        return Ok(Some(Box::new(File::open(blob_path(&entry.hash))?)));
+    };
+
+    let mut file = File::open(archive_file_path(repo_dir))?;
+    let archive_size = file.metadata()?.len();
+    if index.archive_size.is_some_and(|size| size != archive_size) {
+        return Ok(None);
+    }
+    let reader: Box<dyn Read> = match location.compression {
+        EntryCompression::Stored => {
+            file.seek(SeekFrom::Start(location.data_start))?;
+            Box::new(file.take(entry.size))
+        }
+        EntryCompression::Deflated => {
+            file.seek(SeekFrom::Start(location.data_start))?;
+            let compressed = file.take(location.compressed_size);
+            Box::new(flate2::read::DeflateDecoder::new(compressed).take(entry.size))
+        }
+        EntryCompression::Other => {
+            let mut archive = zip::ZipArchive::new(file)?;
+            let mut data = Vec::new();
+            archive.by_index(location.index)?.read_to_end(&mut data)?;
+            Box::new(io::Cursor::new(data))
+        }
+    };
+    Ok(Some(reader))
+}
+
+type SizedReader = (u64, Box<dyn Read>);
+
+/// Size of and a reader over a file of an indexed repo; None for extracted
+/// repos. A repo replaced between loading its index and opening its archive
+/// is read again with the new index.
+fn open_indexed_path(repo_dir: &Path, file_path: &str) -> Result<Option<SizedReader>, RepoError> {
+    for _ in 0..2 {
+        let Some(index) = load_content_index(repo_dir) else {
+            return Ok(None);
+        };
+        let entry = indexed_file(&index, file_path)?;
+        if let Some(reader) = open_indexed_file(repo_dir, &index, entry)? {
+            return Ok(Some((entry.size, reader)));
+        }
+        forget_content_index(repo_dir);
+    }
+    Err(RepoError::IoError(io::Error::other(format!(
+        "{} changed while being read",
+        repo_dir.display()
+    ))))
+}
+
+fn indexed_file<'a>(index: &'a ContentIndex, file_path: &str) -> Result<&'a IndexedFile, RepoError> {
+    index
+        .files
+        .get(file_path.trim_start_matches('/'))
+        .ok_or_else(|| RepoError::IoError(io::Error::new(io::ErrorKind::NotFound, file_path.to_string())))
+}
+
+/// Extracts a tarball into the content folder of `repo_dir`
+pub fn extract_tar_gz(archive_path: &Path, repo_dir: &Path, limits: &ExtractLimits) -> Result<String, RepoError> {
+    let dest_dir = content_dir(repo_dir);
+    let open = || -> Result<tar::Archive<flate2::read::GzDecoder<File>>, RepoError> {
+        Ok(tar::Archive::new(flate2::read::GzDecoder::new(File::open(archive_path)?)))
+    };
+
+    // First pass: validate names and collect them to find the shared root folder.
+    // Tarballs can't be rewound, so the archive is reopened for the extraction.
+    let mut names = Vec::new();
+    for entry in open()?.entries()? {
+        let entry = entry?;
+        if matches!(
+            entry.header().entry_type(),
+            tar::EntryType::Regular | tar::EntryType::Directory | tar::EntryType::Symlink
+        ) {
+            names.push(safe_relative_path(&entry.path()?.to_string_lossy())?);
+            if names.len() > limits.max_entries {
+                return Err(RepoError::UnsafeArchive(format!(
+                    "more than {} entries",
+                    limits.max_entries
+                )));
+            }
+        }
+    }
+    let root_name = common_root(names.iter().map(|n| n.as_str()));
+    let mut budget = ExtractBudget::new(*limits);
+    let mut symlinks = SymlinkMap::new();
+    fs::create_dir_all(&dest_dir)?;
+
+    for entry in open()?.entries()? {
+        let mut entry = entry?;
+        let entry_type = entry.header().entry_type();
+        // Skip hard links, devices and pax metadata headers
+        if !matches!(
+            entry_type,
+            tar::EntryType::Regular | tar::EntryType::Directory | tar::EntryType::Symlink
+        ) {
+            continue;
+        }
+
+        let name = safe_relative_path(&entry.path()?.to_string_lossy())?;
+        let relative_path = match select_entry(&name, root_name.as_deref(), None) {
+            Some(path) => path,
+            None => continue,
+        };
+        budget.add_entry()?;
+
+        let out_path = dest_dir.join(relative_path);
+
+        match entry_type {
+            tar::EntryType::Directory => fs::create_dir_all(&out_path)?,
+            tar::EntryType::Symlink => {
+                let target = entry
+                    .link_name()?
+                    .map(|t| t.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                write_symlink_placeholder(&dest_dir, relative_path, &target, &mut symlinks)?;
+            }
+            _ => {
+                if let Some(parent) = out_path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let mut outfile = File::create(&out_path)?;
+                budget.copy(&mut entry, &mut outfile)?;
+            }
+        }
+    }
+
+    save_symlinks(repo_dir, &symlinks)?;
+
+    Ok(root_name.unwrap_or_default())
+}
+
+/// Copies a checkout on disk into the content folder of `repo_dir`, leaving
+/// out the `.git` directory. Symlinks are recorded rather than followed, so
+/// nothing outside the source folder gets pulled in, and anything but regular
+/// files and folders (FIFOs, sockets, devices) is skipped. The same limits as
+/// for archives apply.
+pub fn copy_directory(src_dir: &Path, repo_dir: &Path, limits: &ExtractLimits) -> Result<(), RepoError> {
+    fn copy_into(
+        src_dir: &Path,
+        dest_root: &Path,
+        relative_dir: &str,
+        budget: &mut ExtractBudget,
+        symlinks: &mut SymlinkMap,
+    ) -> Result<(), RepoError> {
+        fs::create_dir_all(dest_root.join(relative_dir))?;
+
+        for entry in fs::read_dir(src_dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            let name = entry.file_name().to_string_lossy().to_string();
+
+            if name == ".git" {
+                continue;
+            }
+
+            let relative_path = if relative_dir.is_empty() {
+                name
+            } else {
+                format!("{}/{}", relative_dir, name)
+            };
+
+            if file_type.is_symlink() {
+                budget.add_entry()?;
+                let target = fs::read_link(entry.path())?.to_string_lossy().to_string();
+                write_symlink_placeholder(dest_root, &relative_path, &target, symlinks)?;
+            } else if file_type.is_dir() {
+                budget.add_entry()?;
+                copy_into(&entry.path(), dest_root, &relative_path, budget, symlinks)?;
+            } else if file_type.is_file() {
+                budget.add_entry()?;
+                let mut outfile = File::create(dest_root.join(&relative_path))?;
+                budget.copy(&mut File::open(entry.path())?, &mut outfile)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    let mut budget = ExtractBudget::new(*limits);
+    let mut symlinks = SymlinkMap::new();
+    copy_into(src_dir, &content_dir(repo_dir), "", &mut budget, &mut symlinks)?;
+    save_symlinks(repo_dir, &symlinks)
+}
+
+/// Compares the files of two trees. A file counts as modified when its size
+/// changed, or when both sides have a hash and they differ; trees saved before
+/// hashes were recorded fall back to size alone.
+pub fn diff_trees(old: &FileNode, new: &FileNode) -> TreeChanges {
+    fn collect<'a>(node: &'a FileNode, files: &mut HashMap<&'a str, &'a FileNode>) {
+        match &node.children {
+            Some(children) => children.iter().for_each(|child| collect(child, files)),
+            None if !node.is_dir => {
+                files.insert(node.path.as_str(), node);
+            }
+            None => {}
+        }
+    }
+
+    let mut old_files = HashMap::new();
+    let mut new_files = HashMap::new();
+    collect(old, &mut old_files);
+    collect(new, &mut new_files);
+
+    let mut changes = TreeChanges::default();
+    for (path, new_node) in &new_files {
+        match old_files.get(path) {
+            None => changes.added.push(path.to_string()),
+            Some(old_node) => {
+                let hash_changed = matches!(
+                    (&old_node.hash, &new_node.hash),
+                    (Some(a), Some(b)) if a != b
+                );
+                if hash_changed || old_node.size != new_node.size {
+                    changes.modified.push(path.to_string());
+                }
+            }
+        }
+    }
+    changes.removed = old_files
+        .keys()
+        .filter(|path| !new_files.contains_key(*path))
+        .map(|path| path.to_string())
+        .collect();
+
+    changes.added.sort();
+    changes.removed.sort();
+    changes.modified.sort();
+    changes
+}
+
+/// Symlinks and submodules recorded in `_meta`, attached to tree nodes
+struct Annotations {
+    symlinks: SymlinkMap,
+    submodules: SubmoduleMap,
+}
+
+/// Directories first, then by name
+fn sort_nodes(nodes: &mut [FileNode]) {
+    nodes.sort_by(|a, b| match (a.is_dir, b.is_dir) {
+        (true, false) => std::cmp::Ordering::Less,
+        (false, true) => std::cmp::Ordering::Greater,
+        _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+    });
+}
+
+fn dir_node(name: String, relative_path: String, children: Vec<FileNode>, annotations: &Annotations) -> FileNode {
+    FileNode {
+        name,
+        is_dir: true,
+        size: None,
+        is_symlink: false,
+        symlink_target: None,
+        hash: None,
+        submodule: annotations.submodules.get(&relative_path).cloned(),
+        lfs_pointer: None,
+        language: None,
+        line_count: None,
+        is_binary: false,
+        classification: None,
+        total_size: None,
+        file_count: None,
+        path: relative_path,
+        child_count: Some(children.len()),
+        children: Some(children),
+    }
+}
+
+/// A file's node. `stats` is None when the content couldn't be scanned.
+#[allow(clippy::too_many_arguments)]
+fn file_node(
+    name: String,
+    relative_path: String,
+    size: u64,
+    hash: Option<String>,
+    stats: Option<ContentStats>,
+    lfs_pointer: Option<LfsPointer>,
+    filter: &TreeFilter,
+    annotations: &Annotations,
+) -> FileNode {
+    let link = annotations.symlinks.get(&relative_path);
+    let is_binary = is_binary_extension(Path::new(&relative_path)) || stats.is_some_and(|s| s.binary);
+    let language = Some(detect_language(&relative_path)).filter(|language| !is_binary && language != "plaintext");
+    // Symlinks and LFS pointers hold a target or pointer, not the content lines
+    let line_count = stats
+        .filter(|_| !is_binary && link.is_none() && lfs_pointer.is_none())
+        .map(|s| s.line_count);
+    let classification = stats::classify(
+        &relative_path,
+        size,
+        stats.as_ref(),
+        filter.vendored(&relative_path, false),
+        filter.generated(&relative_path, false),
+    );
+
+    FileNode {
+        name,
+        is_dir: false,
+        size: Some(size),
+        is_symlink: link.is_some(),
+        symlink_target: link.and_then(|target| resolve_symlink_target(&relative_path, target)),
+        hash,
+        submodule: None,
+        lfs_pointer,
+        language,
+        line_count,
+        is_binary,
+        classification,
+        total_size: None,
+        file_count: None,
+        path: relative_path,
+        child_count: None,
+        children: None,
+    }
+}
+
+/// Builds the tree of a stored repo, from its files or, for archive-backed
+/// and deduplicated repos, from the content index. Paths hidden by the ignore
+/// rules of `settings`, the repo's own ignore files or `overrides` are left out.
+pub fn build_file_tree(
+    repo_dir: &Path,
+    base_name: &str,
+    settings: &AppSettings,
+    overrides: &[String],
+) -> Result<FileNode, RepoError> {
+    fn build_node(
+        path: &Path,
+        relative_path: String,
+        filter: &mut TreeFilter,
+        annotations: &Annotations,
+    ) -> Option<FileNode> {
+        let name = path.file_name()?.to_string_lossy().to_string();
+        let is_dir = path.is_dir();
+        if !relative_path.is_empty() && filter.is_ignored(&relative_path, is_dir) {
+            return None;
+        }
+
+        if is_dir {
+            // A folder's ignore files apply to everything below it
+            let gitignore = fs::read_to_string(path.join(".gitignore")).ok();
+            let gitattributes = fs::read_to_string(path.join(".gitattributes")).ok();
+            filter.add_repo_files(&relative_path, gitignore.as_deref(), gitattributes.as_deref());
+
+            let mut children: Vec<FileNode> = fs::read_dir(path)
+                .ok()?
+                .filter_map(|e| e.ok())
+                .filter_map(|e| {
+                    let name = e.file_name().to_string_lossy().to_string();
+                    let child_path = if relative_path.is_empty() {
+                        name
+                    } else {
+                        format!("{}/{}", relative_path, name)
+                    };
+                    build_node(&e.path(), child_path, filter, annotations)
+                })
+                .collect();
+            sort_nodes(&mut children);
+
+            Some(dir_node(name, relative_path, children, annotations))
+        } else {
+            let size = fs::metadata(path).ok()?.len();
+            let (hash, stats) = stats::scan_file(path).unzip();
+            Some(file_node(
+                name,
+                relative_path,
+                size,
+                hash,
+                stats,
+                read_lfs_pointer(path),
+                filter,
+                annotations,
+            ))
+        }
+    }
+
+    let annotations = Annotations {
+        symlinks: load_symlinks(repo_dir),
+        submodules: load_submodules(repo_dir),
+    };
+    let mut filter = TreeFilter::new(settings, overrides);
+
+    let root = match load_content_index(repo_dir) {
+        Some(index) => {
+            add_indexed_ignore_files(repo_dir, &index, &mut filter);
+            Some(build_indexed_tree(&index, &filter, &annotations))
+        }
+        None => build_node(&content_dir(repo_dir), String::new(), &mut filter, &annotations),
+    };
+
+    root.map(|mut node| {
+        node.name = base_name.to_string();
+        node.path = "".to_string();
+        summarize_dirs(&mut node);
+        node
+    })
+    .ok_or_else(|| RepoError::IoError(io::Error::new(io::ErrorKind::NotFound, "Root not found")))
+}
+
+/// Fills in each directory's total size, file count and dominant language, and
+/// returns the bytes per language below `node`. Generated, vendored and
+/// minified files don't count towards a language.
+fn summarize_dirs(node: &mut FileNode) -> HashMap<String, u64> {
+    let Some(children) = node.children.as_mut() else {
+        let mut languages = HashMap::new();
+        if let (Some(language), None) = (&node.language, node.classification) {
+            languages.insert(language.clone(), node.size.unwrap_or(0));
+        }
+        return languages;
+    };
+
+    let (mut total_size, mut file_count) = (0, 0);
+    let mut languages: HashMap<String, u64> = HashMap::new();
+    for child in children.iter_mut() {
+        for (language, bytes) in summarize_dirs(child) {
+            *languages.entry(language).or_default() += bytes;
+        }
+        if child.is_dir {
+            total_size += child.total_size.unwrap_or(0);
+            file_count += child.file_count.unwrap_or(0);
+        } else {
+            total_size += child.size.unwrap_or(0);
+            file_count += 1;
+        }
+    }
+
+    node.total_size = Some(total_size);
+    node.file_count = Some(file_count);
+    // Ties go to the alphabetically first, so rebuilds agree
+    node.language = languages
+        .iter()
+        .max_by_key(|(language, bytes)| (**bytes, std::cmp::Reverse(language.as_str())))
+        .map(|(language, _)| language.clone());
+    languages
+}
+
+/// Ignore files bigger than this are not read
+const MAX_IGNORE_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Feeds the `.gitignore` and `.gitattributes` files of an indexed repo to
+/// `filter`, parent folders first
+fn add_indexed_ignore_files(repo_dir: &Path, index: &ContentIndex, filter: &mut TreeFilter) {
+    let mut dirs: Vec<&str> = index
+        .files
+        .keys()
+        .filter_map(|path| {
+            let (dir, name) = path.rsplit_once('/').unwrap_or(("", path));
+            matches!(name, ".gitignore" | ".gitattributes").then_some(dir)
+        })
+        .collect();
+    dirs.sort_by_key(|dir| (dir.split('/').filter(|part| !part.is_empty()).count(), *dir));
+    dirs.dedup();
+
+    let read = |path: String| -> Option<String> {
+        let entry = index.files.get(&path)?;
+        let mut text = String::new();
+        open_indexed_file(repo_dir, index, entry)
+            .ok()
+            .flatten()?
+            .take(MAX_IGNORE_FILE_SIZE)
+            .read_to_string(&mut text)
+            .ok()?;
+        Some(text)
+    };
+    for dir in dirs {
+        let prefix = if dir.is_empty() { String::new() } else { format!("{}/", dir) };
+        let gitignore = read(format!("{}.gitignore", prefix));
+        let gitattributes = read(format!("{}.gitattributes", prefix));
+        filter.add_repo_files(dir, gitignore.as_deref(), gitattributes.as_deref());
+    }
+}
+
+/// Builds the tree of an indexed repo. Folders only implied by file paths,
+/// and submodule folders, are filled in.
+fn build_indexed_tree(index: &ContentIndex, filter: &TreeFilter, annotations: &Annotations) -> FileNode {
+    fn parent_of(path: &str) -> &str {
+        path.rsplit_once('/').map_or("", |(parent, _)| parent)
+    }
+
+    // Children of every folder, "" being the root
+    let mut children: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut dirs: std::collections::HashSet<&str> = std::collections::HashSet::new();
+    let mut seen: std::collections::HashSet<&str> = std::collections::HashSet::new();
+    let paths = index
+        .files
+        .keys()
+        .chain(index.dirs.iter())
+        .chain(annotations.submodules.keys())
+        .map(|p| p.as_str());
+    for path in paths {
+        if index.dirs.contains(path) || annotations.submodules.contains_key(path) {
+            dirs.insert(path);
+        }
+        let mut current = path;
+        while !current.is_empty() && seen.insert(current) {
+            let parent = parent_of(current);
+            children.entry(parent).or_default().push(current);
+            if !parent.is_empty() {
+                dirs.insert(parent);
+            }
+            current = parent;
+        }
+    }
+
+    fn build_node(
+        path: &str,
+        index: &ContentIndex,
+        children: &HashMap<&str, Vec<&str>>,
+        dirs: &std::collections::HashSet<&str>,
+        filter: &TreeFilter,
+        annotations: &Annotations,
+    ) -> Option<FileNode> {
+        let name = path.rsplit('/').next().unwrap_or(path).to_string();
+        let is_dir = path.is_empty() || dirs.contains(path);
+        if !path.is_empty() && filter.is_ignored(path, is_dir) {
+            return None;
+        }
+
+        if is_dir {
+            let mut nodes: Vec<FileNode> = children
+                .get(path)
+                .map(|paths| {
+                    paths
+                        .iter()
+                        .filter_map(|child| build_node(child, index, children, dirs, filter, annotations))
+                        .collect()
+                })
+                .unwrap_or_default();
+            sort_nodes(&mut nodes);
+            return Some(dir_node(name, path.to_string(), nodes, annotations));
+        }
+
+        let entry = index.files.get(path)?;
+        Some(file_node(
+            name,
+            path.to_string(),
+            entry.size,
+            Some(entry.hash.clone()),
+            entry.stats,
+            entry.lfs_pointer.clone(),
+            filter,
+            annotations,
+        ))
+    }
+
+    build_node("", index, &children, &dirs, filter, annotations)
+        .unwrap_or_else(|| dir_node(String::new(), String::new(), Vec::new(), annotations))
+}
+
+pub fn detect_language(file_path: &str) -> String {
+    let ext = Path::new(file_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("");
+
+    match ext.to_lowercase().as_str() {
+        "js" | "mjs" | "cjs" => "javascript",
+        "jsx" => "javascript",
+        "ts" | "mts" | "cts" => "typescript",
+        "tsx" => "typescript",
+        "py" | "pyw" => "python",
+        "json" => "json",
+        "md" | "markdown" => "markdown",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "scss" | "sass" => "scss",
+        "yaml" | "yml" => "yaml",
+        "toml" => "toml",
+        "rs" => "rust",
         "go" => "go",
         "java" => "java",
         "c" | "h" => "c",
@@ -799,29 +2169,132 @@ fn contains_null_bytes(data: &[u8]) -> bool {
     data[..check_size].contains(&0)
 }
 
-pub fn read_file_content(file_path: &Path) -> Result<FileContent, RepoError> {
-    let metadata = fs::metadata(file_path)?;
-    let file_size = metadata.len();
-    let language = detect_language(&file_path.to_string_lossy());
+/// Pointer files are tiny; anything bigger is real content
+const MAX_LFS_POINTER_SIZE: u64 = 1024;
+
+/// Parses a Git LFS pointer: a `version` line naming the LFS spec, then
+/// `oid sha256:<hex>` and `size <bytes>`
+pub fn parse_lfs_pointer(data: &[u8]) -> Option<LfsPointer> {
+    let text = std::str::from_utf8(data).ok()?;
+    let mut lines = text.lines();
+
+    let version = lines.next()?.strip_prefix("version ")?;
+    if !version.starts_with("https://git-lfs.github.com/spec/") && !version.starts_with("https://hawser.github.com/spec/") {
+        return None;
+    }
+
+    let mut oid = None;
+    let mut size = None;
+    for line in lines {
+        if let Some(value) = line.strip_prefix("oid sha256:") {
+            oid = Some(value.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("size ") {
+            size = value.trim().parse().ok();
+        }
+    }
+
+    let oid = oid.filter(|o| o.len() == 64 && o.chars().all(|c| c.is_ascii_hexdigit()))?;
+    Some(LfsPointer { oid, size: size? })
+}
+
+/// Reads the file as an LFS pointer, without loading files too big to be one
+pub fn read_lfs_pointer(file_path: &Path) -> Option<LfsPointer> {
+    if fs::metadata(file_path).ok()?.len() > MAX_LFS_POINTER_SIZE {
+        return None;
+    }
+    parse_lfs_pointer(&fs::read(file_path).ok()?)
+}
+
+/// Fetched LFS objects live under `_meta/lfs`, named by their oid
+pub fn lfs_object_path(repo_dir: &Path, oid: &str) -> PathBuf {
+    repo_dir.join("_meta").join("lfs").join(oid)
+}
+
+/// The LFS pointer stored at a repo path, if that file is one
+pub fn lfs_pointer_at(repo_dir: &Path, file_path: &str) -> Option<LfsPointer> {
+    match load_content_index(repo_dir) {
+        Some(index) => indexed_file(&index, file_path).ok()?.lfs_pointer.clone(),
+        None => read_lfs_pointer(&content_dir(repo_dir).join(file_path)),
+    }
+}
+
+/// The raw bytes to serve for a repo path: the fetched LFS object when the
+/// path is a pointer that has been fetched, the stored file otherwise
+pub fn read_file_bytes(repo_dir: &Path, file_path: &str) -> Result<Vec<u8>, RepoError> {
+    if let Some(pointer) = lfs_pointer_at(repo_dir, file_path) {
+        let object_path = lfs_object_path(repo_dir, &pointer.oid);
+        if object_path.exists() {
+            return Ok(fs::read(object_path)?);
+        }
+    }
+
+    match open_indexed_path(repo_dir, file_path)? {
+        Some((size, mut reader)) => {
+            let mut data = Vec::with_capacity(size as usize);
+            reader.read_to_end(&mut data)?;
+            Ok(data)
+        }
+        None => Ok(fs::read(content_dir(repo_dir).join(file_path))?),
+    }
+}
+
+pub fn read_file_content(repo_dir: &Path, file_path: &str) -> Result<FileContent, RepoError> {
+    let name_path = Path::new(file_path);
+
+    let Some(pointer) = lfs_pointer_at(repo_dir, file_path) else {
+        return read_stored_content(repo_dir, file_path, name_path);
+    };
+
+    // Show the real object once it has been fetched, the pointer text until then
+    let object_path = lfs_object_path(repo_dir, &pointer.oid);
+    let lfs_fetched = object_path.exists();
+    let mut content = if lfs_fetched {
+        read_content(&object_path, name_path)?
+    } else {
+        // Pointer text is plain text whatever the file's extension
+        read_stored_content(repo_dir, file_path, Path::new(""))?
+    };
+    content.lfs_pointer = Some(pointer);
+    content.lfs_fetched = lfs_fetched;
+    Ok(content)
+}
+
+/// Reads a repo file from wherever the repo keeps it: its folder, its archive
+/// or the blob store
+fn read_stored_content(repo_dir: &Path, file_path: &str, name_path: &Path) -> Result<FileContent, RepoError> {
+    match open_indexed_path(repo_dir, file_path)? {
+        Some((size, reader)) => content_from_reader(size, reader, name_path),
+        None => read_content(&content_dir(repo_dir).join(file_path), name_path),
+    }
+}
+
+/// Reads `file_path`, judging binary-ness and language by the name of `name_path`
+fn read_content(file_path: &Path, name_path: &Path) -> Result<FileContent, RepoError> {
+    let file_size = fs::metadata(file_path)?.len();
+    content_from_reader(file_size, File::open(file_path)?, name_path)
+}
+
+fn content_from_reader(file_size: u64, mut reader: impl Read, name_path: &Path) -> Result<FileContent, RepoError> {
+    let language = detect_language(&name_path.to_string_lossy());
 
     // Check if it's a known binary extension
-    if is_binary_extension(file_path) {
+    if is_binary_extension(name_path) {
         return Ok(FileContent {
             content: String::new(),
             truncated: false,
             total_lines: None,
             language,
             is_binary: true,
+            lfs_pointer: None,
+            lfs_fetched: false,
         });
     }
 
     // Check file size
     if file_size > MAX_FILE_SIZE {
         // Read only first portion
-        let mut file = File::open(file_path)?;
-        let mut buffer = vec![0u8; (MAX_FILE_SIZE / 2) as usize];
-        let bytes_read = file.read(&mut buffer)?;
-        buffer.truncate(bytes_read);
+        let mut buffer = Vec::new();
+        reader.take(MAX_FILE_SIZE / 2).read_to_end(&mut buffer)?;
 
         // Check for binary content
         if contains_null_bytes(&buffer) {
@@ -831,66 +2304,322 @@ pub fn read_file_content(file_path: &Path) -> Result<FileContent, RepoError> {
                 total_lines: None,
                 language,
                 is_binary: true,
+                lfs_pointer: None,
+                lfs_fetched: false,
             });
         }
 
-        let content = String::from_utf8_lossy(&buffer);
-        let lines: Vec<&str> = content.lines().take(PREVIEW_LINES).collect();
+        let content = String::from_utf8_lossy(&buffer);
+        let lines: Vec<&str> = content.lines().take(PREVIEW_LINES).collect();
+
+        return Ok(FileContent {
+            content: lines.join("\n"),
+            truncated: true,
+            total_lines: None,
+            language,
+            is_binary: false,
+            lfs_pointer: None,
+            lfs_fetched: false,
+        });
+    }
+
+    // Read file as bytes first to check for binary content
+    let mut bytes = Vec::with_capacity(file_size as usize);
+    reader.read_to_end(&mut bytes)?;
+
+    if contains_null_bytes(&bytes) {
+        return Ok(FileContent {
+            content: String::new(),
+            truncated: false,
+            total_lines: None,
+            language,
+            is_binary: true,
+            lfs_pointer: None,
+            lfs_fetched: false,
+        });
+    }
+
+    let content = String::from_utf8_lossy(&bytes).into_owned();
+    let line_count = content.lines().count();
+
+    if line_count > MAX_LINES {
+        let lines: Vec<&str> = content.lines().take(PREVIEW_LINES).collect();
+        Ok(FileContent {
+            content: lines.join("\n"),
+            truncated: true,
+            total_lines: Some(line_count),
+            language,
+            is_binary: false,
+            lfs_pointer: None,
+            lfs_fetched: false,
+        })
+    } else {
+        Ok(FileContent {
+            content,
+            truncated: false,
+            total_lines: Some(line_count),
+            language,
+            is_binary: false,
+            lfs_pointer: None,
+            lfs_fetched: false,
+        })
+    }
+}
+
+pub fn get_repos_dir() -> PathBuf {
+    paths::data_dir().join("repos")
+}
+
+/// Keeps a readable hint of the repo in its directory name, limited to
+/// characters that are safe on every filesystem
+fn key_slug(text: &str) -> String {
+    let slug: String = text
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '@') { c } else { '-' })
+        .take(64)
+        .collect();
+    // A leading dot would hide the directory and could clash with `.staging`
+    slug.trim_start_matches('.').to_string()
+}
+
+/// The slug alone is lossy and case-insensitive filesystems fold it further,
+/// so the hash of the exact identity is what keeps keys apart
+fn key_hash(parts: &[&str]) -> String {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+    format!("{:x}", hasher.finalize())[..16].to_string()
+}
+
+/// Key for a hosted repo. Each branch, tag or commit and each subfolder import
+/// gets its own copy, so refs can be compared side by side.
+pub fn generate_repo_key(
+    provider: ProviderKind,
+    host: &str,
+    owner: &str,
+    repo: &str,
+    git_ref: &str,
+    subpath: Option<&str>,
+) -> String {
+    let hash = key_hash(&[provider.as_str(), host, owner, repo, git_ref, subpath.unwrap_or("")]);
+    format!("{}-{}", key_slug(&format!("{}-{}@{}", owner, repo, git_ref)), hash)
+}
+
+/// Key for a local directory or archive: its name plus a hash of the full path,
+/// so re-importing the same source replaces it while same-named sources don't collide.
+pub fn generate_local_repo_key(source_path: &Path) -> String {
+    let name = source_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "local".to_string());
+    let hash = key_hash(&["local", &source_path.to_string_lossy()]);
+
+    format!("local-{}-{}", key_slug(&name), hash)
+}
+
+/// The key a cached repo should live under with the current key scheme
+fn repo_key_for(info: &RepoInfo) -> String {
+    match info.source {
+        SourceKind::Remote => generate_repo_key(
+            info.provider,
+            &info.host,
+            &info.owner,
+            &info.repo,
+            &info.branch,
+            info.subpath.as_deref(),
+        ),
+        SourceKind::LocalDirectory | SourceKind::LocalArchive => generate_local_repo_key(Path::new(&info.url)),
+    }
+}
+
+/// Bumped whenever the key scheme changes, so existing caches get renamed once
+const REPO_KEY_VERSION: u32 = 2;
+
+/// Renames cached repos whose directory doesn't match the current key scheme
+/// and rewrites the key in their `_meta/info.json`. Older schemes could map two
+/// repos onto one key; when two old copies now map to the same key, the more
+/// recently imported one is kept.
+pub fn migrate_repo_keys() -> Result<(), RepoError> {
+    let repos_dir = get_repos_dir();
+    let marker = repos_dir.join(".key_version");
+
+    let current = fs::read_to_string(&marker)
+        .ok()
+        .and_then(|v| v.trim().parse::<u32>().ok())
+        .unwrap_or(1);
+    if current >= REPO_KEY_VERSION || !repos_dir.exists() {
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(&repos_dir)? {
+        let path = entry?.path();
+        let mut info = match load_repo_info(&path) {
+            Ok(info) => info,
+            Err(_) => continue,
+        };
+
+        let new_key = repo_key_for(&info);
+        let new_dir = repos_dir.join(&new_key);
+        if new_dir != path {
+            if let Ok(existing) = load_repo_info(&new_dir) {
+                if existing.imported_at >= info.imported_at {
+                    fs::remove_dir_all(&path)?;
+                    continue;
+                }
+                fs::remove_dir_all(&new_dir)?;
+            }
+            fs::rename(&path, &new_dir)?;
+        }
+
+        if info.key != new_key {
+            info.key = new_key;
+            save_repo_info(&new_dir, &info)?;
+        }
+    }
+
+    fs::write(&marker, REPO_KEY_VERSION.to_string())?;
+    Ok(())
+}
+
+/// Bumped whenever the layout of a repo dir changes. Version 2 moved repo
+/// files into the content folder; version 3 replaced `tree.json` with the
+/// indexed tree format; version 4 added file stats to tree nodes.
+const REPO_LAYOUT_VERSION: u32 = 4;
+
+/// Set once `migrate_repo_layout` has returned. Big caches take a while to
+/// migrate, and their trees can't be read until then.
+static LAYOUT_MIGRATION_DONE: AtomicBool = AtomicBool::new(false);
+
+pub fn layout_migration_running() -> bool {
+    !LAYOUT_MIGRATION_DONE.load(Ordering::SeqCst)
+}
+
+/// Moves repo files that older versions kept beside `_meta` into the content
+/// folder, then rebuilds every tree with the current ignore rules and format.
+/// Each repo's step is safe to repeat, and a repo that fails doesn't stop the
+/// others; the version marker is only written once all have migrated, so the
+/// failed ones are retried next launch.
+pub fn migrate_repo_layout(settings: &AppSettings) -> Result<(), RepoError> {
+    let result = migrate_repo_dirs(settings);
+    LAYOUT_MIGRATION_DONE.store(true, Ordering::SeqCst);
+    result
+}
+
+fn migrate_repo_dirs(settings: &AppSettings) -> Result<(), RepoError> {
+    let repos_dir = get_repos_dir();
+    let marker = repos_dir.join(".layout_version");
+
+    let current = fs::read_to_string(&marker)
+        .ok()
+        .and_then(|v| v.trim().parse::<u32>().ok())
+        .unwrap_or(1);
+    if current >= REPO_LAYOUT_VERSION || !repos_dir.exists() {
+        return Ok(());
+    }
+
+    let mut all_migrated = true;
+    for entry in fs::read_dir(&repos_dir)?.filter_map(|e| e.ok()) {
+        let repo_dir = entry.path();
+        let importing = import_running(&entry.file_name().to_string_lossy());
+        if load_repo_info(&repo_dir).is_err() {
+            continue;
+        }
+        // A running import may still fail and leave the old copy, so it's left for next launch
+        if importing || migrate_repo_dir(&repo_dir, current, settings).is_err() {
+            all_migrated = false;
+        }
+    }
 
-        return Ok(FileContent {
-            content: lines.join("\n"),
-            truncated: true,
-            total_lines: None,
-            language,
-            is_binary: false,
-        });
+    if all_migrated {
+        fs::write(&marker, REPO_LAYOUT_VERSION.to_string())?;
     }
+    Ok(())
+}
 
-    // Read file as bytes first to check for binary content
-    let bytes = fs::read(file_path)?;
+fn migrate_repo_dir(repo_dir: &Path, current: u32, settings: &AppSettings) -> Result<(), RepoError> {
+    // Gathered inside `_meta` first, since the repo may have its own folder named like the content dir
+    let gathered = repo_dir.join("_meta").join("layout-migration");
+    let files = content_dir(repo_dir);
+    // A content folder beside nothing but `_meta` was moved by an earlier run
+    let moved = !gathered.exists()
+        && files.is_dir()
+        && fs::read_dir(repo_dir)?
+            .filter_map(|e| e.ok())
+            .all(|e| e.file_name() == "_meta" || e.file_name() == CONTENT_DIR);
+
+    if current < 2 && !moved && load_content_index(repo_dir).is_none() {
+        // Picks up where an interrupted run left off: whatever is still beside `_meta` joins what was gathered
+        fs::create_dir_all(&gathered)?;
+        for entry in fs::read_dir(repo_dir)? {
+            let entry = entry?;
+            if entry.file_name() != "_meta" {
+                fs::rename(entry.path(), gathered.join(entry.file_name()))?;
+            }
+        }
+        fs::rename(&gathered, &files)?;
 
-    if contains_null_bytes(&bytes) {
-        return Ok(FileContent {
-            content: String::new(),
-            truncated: false,
-            total_lines: None,
-            language,
-            is_binary: true,
-        });
+        // Submodules used to be unpacked with their own metadata folder
+        for path in load_submodules(repo_dir).keys() {
+            let _ = fs::remove_dir_all(files.join(path).join("_meta"));
+        }
     }
+    rebuild_tree(repo_dir, settings)?;
+    Ok(())
+}
 
-    let content = String::from_utf8_lossy(&bytes).into_owned();
-    let line_count = content.lines().count();
+/// Tree rebuilds in progress, counted so storage isn't moved out from under them
+static TREE_REBUILDS: AtomicUsize = AtomicUsize::new(0);
 
-    if line_count > MAX_LINES {
-        let lines: Vec<&str> = content.lines().take(PREVIEW_LINES).collect();
-        Ok(FileContent {
-            content: lines.join("\n"),
-            truncated: true,
-            total_lines: Some(line_count),
-            language,
-            is_binary: false,
-        })
-    } else {
-        Ok(FileContent {
-            content,
-            truncated: false,
-            total_lines: Some(line_count),
-            language,
-            is_binary: false,
-        })
+pub fn trees_rebuilding() -> bool {
+    TREE_REBUILDS.load(Ordering::SeqCst) > 0
+}
+
+struct RebuildGuard;
+
+impl RebuildGuard {
+    fn new() -> Self {
+        TREE_REBUILDS.fetch_add(1, Ordering::SeqCst);
+        RebuildGuard
     }
 }
 
-pub fn get_repos_dir() -> PathBuf {
-    directories::ProjectDirs::from("com", "xnu", "RepoRead")
-        .map(|dirs| dirs.data_dir().to_path_buf())
-        .unwrap_or_else(|| PathBuf::from("./repos"))
-        .join("repos")
+impl Drop for RebuildGuard {
+    fn drop(&mut self) {
+        TREE_REBUILDS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Rebuilds and saves a stored repo's tree, e.g. after its ignore rules changed
+pub fn rebuild_tree(repo_dir: &Path, settings: &AppSettings) -> Result<FileNode, RepoError> {
+    let _rebuilding = RebuildGuard::new();
+    let info = load_repo_info(repo_dir)?;
+    // Partial imports are rooted at their subfolder
+    let base_name = info
+        .subpath
+        .as_deref()
+        .and_then(|p| p.rsplit('/').next())
+        .unwrap_or(&info.repo);
+    let tree = build_file_tree(repo_dir, base_name, settings, &info.ignore_patterns)?;
+    save_tree(repo_dir, &tree)?;
+    Ok(tree)
 }
 
-pub fn generate_repo_key(owner: &str, repo: &str) -> String {
-    format!("{}_{}", owner, repo)
+/// Rebuilds the tree of every stored repo. A repo that fails keeps its old
+/// tree. Repos being imported are skipped; their new copy is built with the
+/// current rules anyway.
+pub fn rebuild_trees(settings: &AppSettings) {
+    let Ok(entries) = fs::read_dir(get_repos_dir()) else {
+        return;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        if !import_running(&entry.file_name().to_string_lossy()) {
+            let _ = rebuild_tree(&entry.path(), settings);
+        }
+    }
 }
 
 pub fn save_repo_info(repo_dir: &Path, info: &RepoInfo) -> Result<(), RepoError> {
@@ -905,14 +2634,7 @@ pub fn save_repo_info(repo_dir: &Path, info: &RepoInfo) -> Result<(), RepoError>
 }
 
 pub fn save_tree(repo_dir: &Path, tree: &FileNode) -> Result<(), RepoError> {
-    let meta_dir = repo_dir.join("_meta");
-    fs::create_dir_all(&meta_dir)?;
-
-    let tree_path = meta_dir.join("tree.json");
-    let json = serde_json::to_string_pretty(tree)?;
-    fs::write(tree_path, json)?;
-
-    Ok(())
+    tree_store::write_tree(repo_dir, tree)
 }
 
 pub fn load_repo_info(repo_dir: &Path) -> Result<RepoInfo, RepoError> {
@@ -922,16 +2644,194 @@ pub fn load_repo_info(repo_dir: &Path) -> Result<RepoInfo, RepoError> {
     Ok(info)
 }
 
+/// The whole tree. Use `load_tree_root` and `load_tree_children` to show it,
+/// since big repos have trees too large to send to the UI at once.
 pub fn load_tree(repo_dir: &Path) -> Result<FileNode, RepoError> {
-    let tree_path = repo_dir.join("_meta").join("tree.json");
-    let json = fs::read_to_string(&tree_path)?;
-    let tree: FileNode = serde_json::from_str(&json)?;
-    Ok(tree)
+    tree_store::read_tree(repo_dir)
+}
+
+/// The tree's root node, carrying its child count but not its children
+pub fn load_tree_root(repo_dir: &Path) -> Result<FileNode, RepoError> {
+    tree_store::read_root(repo_dir)
+}
+
+pub fn load_tree_children(repo_dir: &Path, path: &str, offset: usize, limit: usize) -> Result<TreePage, RepoError> {
+    tree_store::read_children(repo_dir, path, offset, limit)
+}
+
+/// Every file path in the tree, for searching it without loading the nodes
+pub fn list_tree_files(repo_dir: &Path) -> Result<Vec<String>, RepoError> {
+    tree_store::read_file_paths(repo_dir)
+}
+
+/// Imports are built here and only moved into `repos/` once complete
+fn get_staging_dir() -> PathBuf {
+    get_repos_dir().join(".staging")
+}
+
+/// Interrupted archive downloads wait here to be resumed
+pub fn get_downloads_dir() -> PathBuf {
+    get_repos_dir().join(".downloads")
+}
+
+/// A scratch copy of a repo being imported. Everything is written here first,
+/// then swapped over `repos/<key>` by `commit`, so a failed or cancelled import
+/// leaves the previous cached copy untouched. Dropping it uncommitted cleans up.
+pub struct StagedImport {
+    dir: PathBuf,
+    archive_path: PathBuf,
+    backup_path: PathBuf,
+    repo_dir: PathBuf,
+    committed: bool,
+}
+
+impl StagedImport {
+    pub fn new(repo_key: &str) -> Result<Self, RepoError> {
+        let staging_dir = get_staging_dir();
+        fs::create_dir_all(&staging_dir)?;
+
+        // Unique per attempt, so overlapping imports of one key never share files
+        let name = format!(
+            "{}-{}-{}",
+            repo_key,
+            std::process::id(),
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        );
+
+        Ok(Self {
+            dir: staging_dir.join(&name),
+            archive_path: staging_dir.join(format!("{}.zip", name)),
+            backup_path: staging_dir.join(format!("{}.old", name)),
+            repo_dir: get_repos_dir().join(repo_key),
+            committed: false,
+        })
+    }
+
+    /// Directory the repo content and metadata are written into
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Where the downloaded archive is kept until extraction
+    pub fn archive_path(&self) -> &Path {
+        &self.archive_path
+    }
+
+    /// Checks the staged copy is readable, then swaps it into place. The old copy
+    /// is moved aside rather than deleted until the new one is in, and put back
+    /// if the swap fails.
+    pub fn commit(mut self) -> Result<(), RepoError> {
+        load_repo_info(&self.dir)?;
+        load_tree_root(&self.dir)?;
+        let _ = fs::remove_file(&self.archive_path);
+
+        let backup = &self.backup_path;
+        let had_previous = self.repo_dir.exists();
+        {
+            let _guard = blob_store_lock().lock().unwrap();
+            if had_previous {
+                fs::rename(&self.repo_dir, backup)?;
+            }
+
+            let swapped = fs::rename(&self.dir, &self.repo_dir);
+            forget_content_index(&self.repo_dir);
+            if let Err(e) = swapped {
+                if had_previous {
+                    let _ = fs::rename(backup, &self.repo_dir);
+                }
+                return Err(e.into());
+            }
+        }
+
+        self.committed = true;
+        if had_previous {
+            // Fetched LFS objects are keyed by content, so they stay valid across
+            // imports. They are only carried over once the new copy is in place.
+            let previous_lfs = backup.join("_meta").join("lfs");
+            let lfs = self.repo_dir.join("_meta").join("lfs");
+            if previous_lfs.is_dir() && !lfs.exists() {
+                let _ = fs::rename(&previous_lfs, &lfs);
+            }
+            let _ = fs::remove_dir_all(backup);
+            // Blobs only the replaced copy used can go now
+            collect_blobs();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for StagedImport {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.archive_path);
+        if !self.committed {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+/// Removes staging dirs and downloads left behind by imports that crashed or
+/// were killed. A previous copy an import had moved aside is put back first if
+/// the new one never made it into place. Only call this at startup, before any
+/// import can be running.
+pub fn cleanup_stale_imports() {
+    if let Ok(entries) = fs::read_dir(get_staging_dir()) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if !path.is_dir() || path.extension().is_none_or(|ext| ext != "old") {
+                continue;
+            }
+            if let Ok(info) = load_repo_info(&path) {
+                let repo_dir = get_repos_dir().join(repo_key_for(&info));
+                if !repo_dir.exists() {
+                    let _ = fs::rename(&path, &repo_dir);
+                }
+            }
+        }
+    }
+    let _ = fs::remove_dir_all(get_staging_dir());
+    // Including blobs stored by those imports
+    collect_blobs();
+
+    // Interrupted downloads are kept for resuming, but not forever
+    const PARTIAL_DOWNLOAD_MAX_AGE: std::time::Duration = std::time::Duration::from_secs(7 * 24 * 60 * 60);
+    if let Ok(entries) = fs::read_dir(get_downloads_dir()) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let expired = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .is_none_or(|age| age > PARTIAL_DOWNLOAD_MAX_AGE);
+            if expired {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    // Older versions downloaded archives straight into `repos/`
+    if let Ok(entries) = fs::read_dir(get_repos_dir()) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "zip") {
+                let _ = fs::remove_file(&path);
+            }
+        }
+    }
 }
 
 const MAX_RECENT_REPOS: usize = 100;
 
-pub fn list_repos() -> Result<Vec<RepoInfo>, RepoError> {
+/// All cached refs of one repository, most recently opened first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepoGroup {
+    pub provider: ProviderKind,
+    pub host: String,
+    pub owner: String,
+    pub repo: String,
+    pub refs: Vec<RepoInfo>,
+}
+
+pub fn list_repos() -> Result<Vec<RepoGroup>, RepoError> {
     let repos_dir = get_repos_dir();
 
     if !repos_dir.exists() {
@@ -958,10 +2858,34 @@ pub fn list_repos() -> Result<Vec<RepoInfo>, RepoError> {
         b_time.cmp(a_time)
     });
 
+    // Group refs of the same repository, keeping groups in order of their latest ref.
+    // Local sources are never grouped, they only share a name by coincidence.
+    let mut groups: Vec<RepoGroup> = vec![];
+    let mut group_index: HashMap<String, usize> = HashMap::new();
+    for info in repos {
+        let group_key = match info.source {
+            SourceKind::Remote => format!("{}/{}/{}", info.host, info.owner, info.repo),
+            _ => info.key.clone(),
+        };
+        match group_index.get(&group_key) {
+            Some(&i) => groups[i].refs.push(info),
+            None => {
+                group_index.insert(group_key, groups.len());
+                groups.push(RepoGroup {
+                    provider: info.provider,
+                    host: info.host.clone(),
+                    owner: info.owner.clone(),
+                    repo: info.repo.clone(),
+                    refs: vec![info],
+                });
+            }
+        }
+    }
+
     // Limit to MAX_RECENT_REPOS
-    repos.truncate(MAX_RECENT_REPOS);
+    groups.truncate(MAX_RECENT_REPOS);
 
-    Ok(repos)
+    Ok(groups)
 }
 
 pub fn update_repo_last_opened(repo_key: &str) -> Result<(), RepoError> {
@@ -986,14 +2910,206 @@ pub fn delete_repo(repo_key: &str) -> Result<(), RepoError> {
     }
 
     fs::remove_dir_all(&repo_dir)?;
+    forget_content_index(&repo_dir);
+    collect_blobs();
     Ok(())
 }
 
+pub fn set_repo_pinned(repo_key: &str, pinned: bool) -> Result<(), RepoError> {
+    let repo_dir = get_repos_dir().join(repo_key);
+
+    if !repo_dir.exists() {
+        return Err(RepoError::RepoNotFound(repo_key.to_string()));
+    }
+
+    let mut info = load_repo_info(&repo_dir)?;
+    info.pinned = pinned;
+    save_repo_info(&repo_dir, &info)
+}
+
+/// Saves a repo's own ignore patterns, which take precedence over its ignore
+/// files and the global patterns, and returns the root of its rebuilt tree
+pub fn set_repo_ignore_patterns(
+    repo_key: &str,
+    patterns: Vec<String>,
+    settings: &AppSettings,
+) -> Result<FileNode, RepoError> {
+    let repo_dir = get_repos_dir().join(repo_key);
+
+    if !repo_dir.exists() {
+        return Err(RepoError::RepoNotFound(repo_key.to_string()));
+    }
+    // The import would replace the copy, and its info, being rebuilt here
+    if import_running(repo_key) {
+        return Err(RepoError::ImportRunning(format!("An import of {}", repo_key)));
+    }
+
+    let mut info = load_repo_info(&repo_dir)?;
+    info.ignore_patterns = patterns
+        .into_iter()
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect();
+    save_repo_info(&repo_dir, &info)?;
+    rebuild_tree(&repo_dir, settings).map(|tree| tree_store::without_children(&tree))
+}
+
+/// Disk space taken by one cached repo
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepoUsage {
+    pub key: String,
+    pub owner: String,
+    pub repo: String,
+    pub branch: String,
+    /// Bytes in the repo's own folder, including a kept archive and fetched LFS objects
+    pub size_bytes: u64,
+    /// Bytes of blob store contents the repo refers to; other repos may share them
+    pub shared_bytes: u64,
+    pub imported_at: String,
+    pub last_opened_at: Option<String>,
+    pub pinned: bool,
+    pub favorite: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageUsage {
+    /// Every cached repo, largest first
+    pub repos: Vec<RepoUsage>,
+    pub blob_store_bytes: u64,
+    /// Everything under the repos folder, including imports in progress and
+    /// interrupted downloads
+    pub total_bytes: u64,
+    pub quota_bytes: Option<u64>,
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => dir_size(&entry.path()),
+            Ok(_) => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// Favorites only record owner and name, so any remote ref of that repo counts
+fn is_favorite(info: &RepoInfo, favorites: &[FavoriteRepo]) -> bool {
+    info.source == SourceKind::Remote
+        && favorites
+            .iter()
+            .any(|fav| fav.owner.eq_ignore_ascii_case(&info.owner) && fav.repo.eq_ignore_ascii_case(&info.repo))
+}
+
+fn repo_usages() -> Result<Vec<RepoUsage>, RepoError> {
+    let repos_dir = get_repos_dir();
+    if !repos_dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let favorites = load_favorites();
+    let mut usages = vec![];
+    for entry in fs::read_dir(&repos_dir)? {
+        let path = entry?.path();
+        let Ok(info) = load_repo_info(&path) else {
+            continue;
+        };
+
+        // Each blob counts once per repo, however many paths share it
+        let shared_bytes = load_content_index(&path)
+            .map(|index| {
+                let mut seen = std::collections::HashSet::new();
+                index
+                    .files
+                    .values()
+                    .filter(|file| file.archive.is_none() && seen.insert(file.hash.as_str()))
+                    .map(|file| file.size)
+                    .sum()
+            })
+            .unwrap_or(0);
+
+        usages.push(RepoUsage {
+            favorite: is_favorite(&info, &favorites),
+            size_bytes: dir_size(&path),
+            shared_bytes,
+            key: info.key,
+            owner: info.owner,
+            repo: info.repo,
+            branch: info.branch,
+            imported_at: info.imported_at,
+            last_opened_at: info.last_opened_at,
+            pinned: info.pinned,
+        });
+    }
+    Ok(usages)
+}
+
+fn quota_bytes(settings: &AppSettings) -> Option<u64> {
+    settings.cache_quota_mb.map(|mb| mb.saturating_mul(1024 * 1024))
+}
+
+/// Reports the disk space of every cached repo, unlike `list_repos` which only
+/// lists the most recent ones
+pub fn storage_usage(settings: &AppSettings) -> Result<StorageUsage, RepoError> {
+    let mut repos = repo_usages()?;
+    repos.sort_by_key(|repo| std::cmp::Reverse(repo.size_bytes + repo.shared_bytes));
+
+    Ok(StorageUsage {
+        repos,
+        blob_store_bytes: dir_size(&get_blobs_dir()),
+        total_bytes: dir_size(&get_repos_dir()),
+        quota_bytes: quota_bytes(settings),
+    })
+}
+
+/// Deletes the least recently opened repos until the cache fits its quota.
+/// Favorites, pinned repos and `keep` are never evicted, so the cache may stay
+/// over quota. Only repo folders and the blob store count towards it, since
+/// imports in progress and kept partial downloads can't be evicted. Returns
+/// the keys of the evicted repos.
+pub fn enforce_cache_quota(settings: &AppSettings, keep: Option<&str>) -> Result<Vec<String>, RepoError> {
+    let Some(quota) = quota_bytes(settings) else {
+        return Ok(vec![]);
+    };
+    let usages = repo_usages()?;
+    let mut total = usages.iter().map(|repo| repo.size_bytes).sum::<u64>() + dir_size(&get_blobs_dir());
+    if total <= quota {
+        return Ok(vec![]);
+    }
+
+    let mut candidates: Vec<RepoUsage> = usages
+        .into_iter()
+        .filter(|repo| !repo.pinned && !repo.favorite && Some(repo.key.as_str()) != keep)
+        .collect();
+    candidates.sort_by(|a, b| {
+        let a_time = a.last_opened_at.as_ref().unwrap_or(&a.imported_at);
+        let b_time = b.last_opened_at.as_ref().unwrap_or(&b.imported_at);
+        a_time.cmp(b_time)
+    });
+
+    let mut evicted = vec![];
+    for repo in candidates {
+        if total <= quota {
+            break;
+        }
+
+        // Shared blobs are only freed once no other repo uses them
+        let blobs_before = if repo.shared_bytes > 0 { dir_size(&get_blobs_dir()) } else { 0 };
+        delete_repo(&repo.key)?;
+        total = total.saturating_sub(repo.size_bytes);
+        if repo.shared_bytes > 0 {
+            total = total.saturating_sub(blobs_before.saturating_sub(dir_size(&get_blobs_dir())));
+        }
+        evicted.push(repo.key);
+    }
+    Ok(evicted)
+}
+
 pub fn get_screenshots_dir() -> PathBuf {
-    directories::ProjectDirs::from("com", "xnu", "RepoRead")
-        .map(|dirs| dirs.data_dir().to_path_buf())
-        .unwrap_or_else(|| PathBuf::from("./data"))
-        .join("screenshots")
+    paths::data_dir().join("screenshots")
 }
 
 pub fn save_screenshot(base64_data: &str, filename: &str, copy_to_clipboard: bool) -> Result<String, RepoError> {
@@ -1078,7 +3194,7 @@ pub async fn interpret_code(
         }],
     };
 
-    let client = reqwest::Client::new();
+    let client = http::client();
     let response = client
         .post("https://openrouter.ai/api/v1/chat/completions")
         .header("Content-Type", "application/json")
@@ -1136,7 +3252,9 @@ struct GistResponse {
     html_url: String,
 }
 
+/// Creates a gist through the GitHub API at `api_base`
 pub async fn create_gist(
+    api_base: &str,
     token: &str,
     filename: &str,
     content: &str,
@@ -1157,10 +3275,9 @@ pub async fn create_gist(
         files,
     };
 
-    let client = reqwest::Client::new();
+    let client = http::client();
     let response = client
-        .post("https://api.github.com/gists")
-        .header("User-Agent", "RepoRead/0.1")
+        .post(format!("{}/gists", api_base.trim_end_matches('/')))
         .header("Accept", "application/vnd.github.v3+json")
         .header("Authorization", format!("Bearer {}", token))
         .json(&request)
@@ -1224,10 +3341,7 @@ struct AllChatHistory {
 }
 
 fn get_chat_history_path() -> PathBuf {
-    directories::ProjectDirs::from("com", "xnu", "RepoRead")
-        .map(|dirs| dirs.config_dir().to_path_buf())
-        .unwrap_or_else(|| PathBuf::from("./config"))
-        .join("chat_history.json")
+    paths::config_dir().join("chat_history.json")
 }
 
 fn load_all_chat_history() -> AllChatHistory {
@@ -1310,3 +3424,326 @@ pub fn delete_chat_session(repo_url: &str, session_id: &str) -> Result<(), RepoE
 
     save_all_chat_history(&all)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn safe_relative_path_normalizes_harmless_names() {
+        assert_eq!(safe_relative_path("src/main.rs").unwrap(), "src/main.rs");
+        assert_eq!(safe_relative_path("./src//main.rs").unwrap(), "src/main.rs");
+        assert_eq!(safe_relative_path("src\\lib\\mod.rs").unwrap(), "src/lib/mod.rs");
+        assert_eq!(safe_relative_path("docs/").unwrap(), "docs");
+        assert_eq!(safe_relative_path("a..b/c").unwrap(), "a..b/c");
+    }
+
+    #[test]
+    fn safe_relative_path_rejects_escaping_names() {
+        for name in [
+            "../evil",
+            "a/../../evil",
+            "a/..",
+            "..\\evil",
+            "a\\..\\..\\evil",
+            "/etc/passwd",
+            "\\windows\\system32",
+            "C:\\evil",
+            "C:/evil",
+            "c:evil",
+            "\\\\server\\share\\evil",
+        ] {
+            assert!(
+                matches!(safe_relative_path(name), Err(RepoError::UnsafeArchive(_))),
+                "{} was accepted",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn resolve_symlink_target_stays_inside_the_repo() {
+        assert_eq!(resolve_symlink_target("a/link", "b.txt").as_deref(), Some("a/b.txt"));
+        assert_eq!(resolve_symlink_target("a/b/link", "../c/./d").as_deref(), Some("a/c/d"));
+        assert_eq!(resolve_symlink_target("link", "dir\\file").as_deref(), Some("dir/file"));
+        assert_eq!(resolve_symlink_target("a/link", "../top").as_deref(), Some("top"));
+        assert_eq!(resolve_symlink_target("a/link", "../../outside"), None);
+        assert_eq!(resolve_symlink_target("link", ".."), None);
+        assert_eq!(resolve_symlink_target("a/link", "/etc/passwd"), None);
+        assert_eq!(resolve_symlink_target("a/link", "\\etc"), None);
+    }
+
+    #[test]
+    fn select_entry_strips_the_root_and_subpath() {
+        assert_eq!(select_entry("repo-main/src/a.rs", Some("repo-main"), None), Some("src/a.rs"));
+        assert_eq!(select_entry("repo-main/", Some("repo-main"), None), None);
+        assert_eq!(select_entry("repo-main", Some("repo-main"), None), None);
+        assert_eq!(select_entry("repo-mainx/a.rs", Some("repo-main"), None), Some("repo-mainx/a.rs"));
+        assert_eq!(select_entry("src/a.rs", None, None), Some("src/a.rs"));
+        assert_eq!(select_entry("repo-main/src/a.rs", Some("repo-main"), Some("src")), Some("a.rs"));
+        assert_eq!(select_entry("repo-main/src", Some("repo-main"), Some("src")), None);
+        assert_eq!(select_entry("repo-main/srcx/a.rs", Some("repo-main"), Some("src")), None);
+        assert_eq!(select_entry("repo-main/lib/a.rs", Some("repo-main"), Some("src")), None);
+    }
+
+    #[test]
+    fn common_root_needs_one_shared_folder() {
+        assert_eq!(common_root(["r/a", "r/b/c"]).as_deref(), Some("r"));
+        assert_eq!(common_root(["r/a", "s/b"]), None);
+        assert_eq!(common_root(["r/a", "top.txt"]), None);
+        assert_eq!(common_root(["/abs/a"]), None);
+        // Directory entries reach it without their trailing `/`
+        assert_eq!(common_root(["r", "r/src", "r/src/a.rs"]).as_deref(), Some("r"));
+        assert_eq!(common_root(["r/", "r/a"]).as_deref(), Some("r"));
+        assert_eq!(common_root(["r", "s/a"]), None);
+        assert_eq!(common_root(["only.txt"]), None);
+    }
+
+    #[test]
+    fn parse_gitmodules_reads_path_and_url() {
+        let text = r#"
+# comment
+[submodule "libs/a"]
+	path = libs/a
+	url = https://github.com/o/a.git
+[core]
+	path = not/a/submodule
+	url = https://example.com/x
+[submodule "b"]
+	; no url, dropped
+	path = b
+[submodule "quoted"]
+	url = "../quoted.git"
+	path = "vendor/quoted"
+"#;
+        let submodules: Vec<(String, String)> = parse_gitmodules(text)
+            .into_iter()
+            .map(|s| (s.path, s.url))
+            .collect();
+        assert_eq!(
+            submodules,
+            [
+                ("libs/a".to_string(), "https://github.com/o/a.git".to_string()),
+                ("vendor/quoted".to_string(), "../quoted.git".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_gitmodules_drops_paths_leaving_the_repo() {
+        let text = "[submodule \"x\"]\npath = ../x\nurl = u\n\
+                    [submodule \"y\"]\npath = /etc\nurl = u\n\
+                    [submodule \"z\"]\npath = .\nurl = u\n\
+                    [submodule \"w\"]\npath = C:\\w\nurl = u\n";
+        assert!(parse_gitmodules(text).is_empty());
+    }
+
+    const OID: &str = "4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393";
+
+    #[test]
+    fn parse_lfs_pointer_reads_oid_and_size() {
+        let text = format!(
+            "version https://git-lfs.github.com/spec/v1\noid sha256:{}\nsize 12345\n",
+            OID
+        );
+        let pointer = parse_lfs_pointer(text.as_bytes()).unwrap();
+        assert_eq!(pointer.oid, OID);
+        assert_eq!(pointer.size, 12345);
+
+        let legacy = format!("version https://hawser.github.com/spec/v1\nsize 1\noid sha256:{}", OID);
+        assert!(parse_lfs_pointer(legacy.as_bytes()).is_some());
+    }
+
+    #[test]
+    fn parse_lfs_pointer_rejects_other_content() {
+        let version = "version https://git-lfs.github.com/spec/v1";
+        for text in [
+            String::new(),
+            "just a text file".to_string(),
+            format!("oid sha256:{}\nsize 1\n{}", OID, version),
+            format!("version https://example.com/spec/v1\noid sha256:{}\nsize 1", OID),
+            format!("{}\noid sha256:{}", version, OID),
+            format!("{}\noid sha256:{}\nsize lots", version, OID),
+            format!("{}\noid sha256:abc123\nsize 1", version),
+            format!("{}\noid sha256:{}\nsize 1", version, OID.replace('4', "g")),
+            format!("{}\noid sha1:{}\nsize 1", version, &OID[..40]),
+        ] {
+            assert!(parse_lfs_pointer(text.as_bytes()).is_none(), "{:?} was accepted", text);
+        }
+        assert!(parse_lfs_pointer(&[0xff, 0xfe, 0x00]).is_none());
+    }
+
+    fn node(path: &str, size: Option<u64>, hash: Option<&str>, children: Option<Vec<FileNode>>) -> FileNode {
+        FileNode {
+            name: path.rsplit('/').next().unwrap_or(path).to_string(),
+            path: path.to_string(),
+            is_dir: children.is_some(),
+            size,
+            is_symlink: false,
+            symlink_target: None,
+            hash: hash.map(str::to_string),
+            submodule: None,
+            lfs_pointer: None,
+            language: None,
+            line_count: None,
+            is_binary: false,
+            classification: None,
+            total_size: None,
+            file_count: None,
+            child_count: children.as_ref().map(Vec::len),
+            children,
+        }
+    }
+
+    fn file(path: &str, size: u64, hash: Option<&str>) -> FileNode {
+        node(path, Some(size), hash, None)
+    }
+
+    fn dir(path: &str, children: Vec<FileNode>) -> FileNode {
+        node(path, None, None, Some(children))
+    }
+
+    #[test]
+    fn diff_trees_finds_added_removed_and_modified_files() {
+        let old = dir(
+            "",
+            vec![
+                dir("src", vec![file("src/a.rs", 10, Some("aa")), file("src/b.rs", 5, Some("bb"))]),
+                file("README.md", 3, Some("rr")),
+                file("old.txt", 1, Some("oo")),
+            ],
+        );
+        let new = dir(
+            "",
+            vec![
+                dir(
+                    "src",
+                    vec![
+                        file("src/a.rs", 10, Some("a2")),
+                        file("src/b.rs", 5, Some("bb")),
+                        file("src/c.rs", 7, Some("cc")),
+                    ],
+                ),
+                file("README.md", 4, Some("r2")),
+                dir("empty", vec![]),
+            ],
+        );
+
+        let changes = diff_trees(&old, &new);
+        assert_eq!(changes.added, ["src/c.rs"]);
+        assert_eq!(changes.removed, ["old.txt"]);
+        assert_eq!(changes.modified, ["README.md", "src/a.rs"]);
+    }
+
+    #[test]
+    fn diff_trees_falls_back_to_size_without_hashes() {
+        let old = dir("", vec![file("same", 1, None), file("grown", 1, None), file("rehashed", 1, Some("x"))]);
+        let new = dir("", vec![file("same", 1, Some("y")), file("grown", 2, None), file("rehashed", 1, None)]);
+
+        let changes = diff_trees(&old, &new);
+        assert!(changes.added.is_empty() && changes.removed.is_empty());
+        assert_eq!(changes.modified, ["grown"]);
+    }
+
+    /// A scratch folder under the system temp dir, emptied first
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("reporead-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A zip shaped like the ones GitHub serves, with an entry for every folder
+    fn write_codeload_zip(path: &Path) {
+        use std::io::Write;
+        use zip::write::SimpleFileOptions;
+
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        let options = SimpleFileOptions::default();
+        for dir in ["repo-main/", "repo-main/src/", "repo-main/src/nested/", "repo-main/docs/"] {
+            zip.add_directory(dir, options).unwrap();
+        }
+        for (name, content) in [
+            ("repo-main/README.md", "readme"),
+            ("repo-main/src/lib.rs", "lib"),
+            ("repo-main/src/nested/mod.rs", "nested"),
+            ("repo-main/docs/guide.md", "guide"),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    const TEST_LIMITS: ExtractLimits = ExtractLimits {
+        max_total_bytes: 1024 * 1024,
+        max_entries: 100,
+    };
+
+    #[test]
+    fn extract_zip_strips_the_root_folder() {
+        let dir = scratch_dir("extract-root");
+        let zip_path = dir.join("repo.zip");
+        write_codeload_zip(&zip_path);
+        let repo_dir = dir.join("repo");
+
+        let root = extract_zip(&zip_path, &repo_dir, None, &TEST_LIMITS).unwrap();
+        assert_eq!(root, "repo-main");
+        let content = content_dir(&repo_dir);
+        assert_eq!(fs::read_to_string(content.join("README.md")).unwrap(), "readme");
+        assert_eq!(fs::read_to_string(content.join("src/nested/mod.rs")).unwrap(), "nested");
+        assert!(!content.join("repo-main").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn extract_zip_keeps_only_the_requested_subfolder() {
+        let dir = scratch_dir("extract-subpath");
+        let zip_path = dir.join("repo.zip");
+        write_codeload_zip(&zip_path);
+        let repo_dir = dir.join("repo");
+
+        extract_zip(&zip_path, &repo_dir, Some("src"), &TEST_LIMITS).unwrap();
+        let content = content_dir(&repo_dir);
+        assert_eq!(fs::read_to_string(content.join("lib.rs")).unwrap(), "lib");
+        assert_eq!(fs::read_to_string(content.join("nested/mod.rs")).unwrap(), "nested");
+        assert!(!content.join("README.md").exists());
+        assert!(!content.join("docs").exists());
+
+        let missing = extract_zip(&zip_path, &dir.join("missing"), Some("nope"), &TEST_LIMITS);
+        assert!(matches!(missing, Err(RepoError::RepoNotFound(_))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn copy_directory_skips_special_files_and_applies_limits() {
+        let dir = scratch_dir("copy-directory");
+        let src = dir.join("src");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::create_dir_all(src.join(".git")).unwrap();
+        fs::write(src.join("a.txt"), "a").unwrap();
+        fs::write(src.join("sub/b.txt"), "b").unwrap();
+        fs::write(src.join(".git/HEAD"), "ref").unwrap();
+        // Opening a FIFO for reading blocks until something writes to it
+        #[cfg(unix)]
+        assert!(std::process::Command::new("mkfifo")
+            .arg(src.join("pipe"))
+            .status()
+            .is_ok_and(|status| status.success()));
+
+        let repo_dir = dir.join("repo");
+        copy_directory(&src, &repo_dir, &TEST_LIMITS).unwrap();
+        let content = content_dir(&repo_dir);
+        assert_eq!(fs::read_to_string(content.join("sub/b.txt")).unwrap(), "b");
+        assert!(!content.join(".git").exists());
+        assert!(!content.join("pipe").exists());
+
+        let tight = ExtractLimits {
+            max_total_bytes: 1024,
+            max_entries: 2,
+        };
+        let limited = copy_directory(&src, &dir.join("limited"), &tight);
+        assert!(matches!(limited, Err(RepoError::UnsafeArchive(_))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
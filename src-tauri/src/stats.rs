@@ -0,0 +1,143 @@
+//! What the tree shows about a file beyond its size: line count, whether it
+//! is binary, and whether it is generated, vendored or minified. Contents are
+//! scanned once, while they are hashed, so the tree never rereads files.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// Bytes at the start of a file checked for NULs and generated-code markers
+const HEAD_SIZE: usize = 8192;
+/// Text files averaging longer lines than this are taken for minified
+const MINIFIED_AVERAGE_LINE: u64 = 200;
+/// Smaller files are never called minified
+const MINIFIED_MIN_SIZE: u64 = 1024;
+
+/// Comments code generators leave near the top of what they write
+const GENERATED_MARKERS: [&str; 5] = [
+    "@generated",
+    "do not edit",
+    "code generated by",
+    "autogenerated",
+    "auto-generated",
+];
+/// Lock files and other generator output recognized by name
+const GENERATED_NAMES: [&str; 9] = [
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "Cargo.lock",
+    "Gemfile.lock",
+    "composer.lock",
+    "poetry.lock",
+    "Pipfile.lock",
+    "go.sum",
+];
+const GENERATED_SUFFIXES: [&str; 4] = [".pb.go", "_pb2.py", ".g.dart", ".designer.cs"];
+/// Folders of third-party code, wherever they appear in a path
+const VENDORED_DIRS: [&str; 5] = ["vendor", "node_modules", "third_party", "third-party", "bower_components"];
+
+/// Why a file is usually not worth reading
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileClass {
+    Generated,
+    Vendored,
+    Minified,
+}
+
+/// What a scan of a file's content found
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ContentStats {
+    pub line_count: u64,
+    pub binary: bool,
+    /// A generated-code marker appears near the top
+    pub generated: bool,
+}
+
+/// Hashes content written to it and gathers its `ContentStats` on the way
+#[derive(Default)]
+pub struct FileScanner {
+    hasher: Sha256,
+    bytes: u64,
+    newlines: u64,
+    ends_with_newline: bool,
+    head: Vec<u8>,
+}
+
+impl FileScanner {
+    /// The SHA-256 of the content, and its stats
+    pub fn finish(self) -> (String, ContentStats) {
+        let binary = self.head.contains(&0);
+        let head = String::from_utf8_lossy(&self.head).to_lowercase();
+        let stats = ContentStats {
+            line_count: self.newlines + u64::from(self.bytes > 0 && !self.ends_with_newline),
+            binary,
+            generated: !binary && GENERATED_MARKERS.iter().any(|marker| head.contains(marker)),
+        };
+        (format!("{:x}", self.hasher.finalize()), stats)
+    }
+}
+
+impl io::Write for FileScanner {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.hasher.update(buf);
+        self.bytes += buf.len() as u64;
+        self.newlines += buf.iter().filter(|&&b| b == b'\n').count() as u64;
+        if let Some(&last) = buf.last() {
+            self.ends_with_newline = last == b'\n';
+        }
+        let room = HEAD_SIZE.saturating_sub(self.head.len());
+        self.head.extend_from_slice(&buf[..room.min(buf.len())]);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Hashes and scans the file at `path`
+pub fn scan_file(path: &Path) -> Option<(String, ContentStats)> {
+    let mut scanner = FileScanner::default();
+    io::copy(&mut File::open(path).ok()?, &mut scanner).ok()?;
+    Some(scanner.finish())
+}
+
+/// Whether a path lies in a folder conventionally holding third-party code
+pub fn is_vendored_path(path: &str) -> bool {
+    let mut folders = path.split('/');
+    folders.next_back();
+    folders.any(|folder| VENDORED_DIRS.contains(&folder))
+}
+
+/// Classifies a file from its path and content. `vendored` and `generated`
+/// are what `.gitattributes` says, which wins over guessing.
+pub fn classify(
+    path: &str,
+    size: u64,
+    stats: Option<&ContentStats>,
+    vendored: Option<bool>,
+    generated: Option<bool>,
+) -> Option<FileClass> {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    if vendored.unwrap_or_else(|| is_vendored_path(path)) {
+        return Some(FileClass::Vendored);
+    }
+    let looks_generated = GENERATED_NAMES.contains(&name)
+        || GENERATED_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+        || stats.is_some_and(|s| s.generated);
+    if generated.unwrap_or(looks_generated) {
+        return Some(FileClass::Generated);
+    }
+
+    let long_lines = stats.is_some_and(|s| {
+        !s.binary && size >= MINIFIED_MIN_SIZE && size / s.line_count.max(1) > MINIFIED_AVERAGE_LINE
+    });
+    if name.contains(".min.") || long_lines {
+        return Some(FileClass::Minified);
+    }
+    None
+}
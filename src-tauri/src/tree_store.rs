@@ -0,0 +1,301 @@
+//! On-disk format of a repo's file tree, built for reading one folder at a
+//! time. `_meta/tree.jsonl` holds every node without its children, one JSON
+//! line each, with the children of a folder written next to each other.
+//! `_meta/tree-index.json` holds the root node and, for each folder, where its
+//! run of lines starts and ends, so listing a folder reads only those lines.
+//! Both files carry the generation they were written in, so a reader never
+//! pairs an index with data from another write.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use crate::repo::{FileNode, RepoError};
+
+const TREE_DATA_FILE: &str = "tree.jsonl";
+const TREE_INDEX_FILE: &str = "tree-index.json";
+/// Written by versions before the indexed format
+const LEGACY_TREE_FILE: &str = "tree.json";
+/// Every this many lines of a folder, the index records where the line starts
+const OFFSET_STRIDE: usize = 64;
+/// The header line is short; anything longer is a node from before headers
+const MAX_HEADER_SIZE: u64 = 256;
+/// Tries at finding the data file and index from the same write
+const OPEN_ATTEMPTS: u32 = 20;
+const OPEN_RETRY_DELAY: Duration = Duration::from_millis(5);
+
+/// Byte range of a folder's child lines in the data file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DirSpan {
+    start: u64,
+    end: u64,
+    count: usize,
+    /// Offset of every `OFFSET_STRIDE`th line, starting with the first.
+    /// Trees written before these existed have none.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    offsets: Vec<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TreeIndex {
+    /// Matches the data file's header line; `None` for trees written before
+    /// either had one
+    #[serde(default)]
+    generation: Option<String>,
+    root: FileNode,
+    /// Keyed by repo-relative folder path, "" for the root
+    dirs: HashMap<String, DirSpan>,
+}
+
+/// First line of the data file
+#[derive(Serialize, Deserialize)]
+struct DataHeader {
+    generation: String,
+}
+
+/// One page of a folder's children, in tree order. Child folders come without
+/// their children; `child_count` says how many they have.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreePage {
+    pub path: String,
+    pub offset: usize,
+    pub total: usize,
+    pub children: Vec<FileNode>,
+}
+
+fn data_path(repo_dir: &Path) -> PathBuf {
+    repo_dir.join("_meta").join(TREE_DATA_FILE)
+}
+
+fn index_path(repo_dir: &Path) -> PathBuf {
+    repo_dir.join("_meta").join(TREE_INDEX_FILE)
+}
+
+/// A node as stored: everything but its children
+pub fn without_children(node: &FileNode) -> FileNode {
+    FileNode {
+        children: None,
+        ..node.clone()
+    }
+}
+
+/// Writes `tree` in the indexed format, replacing any tree saved before
+pub fn write_tree(repo_dir: &Path, tree: &FileNode) -> Result<(), RepoError> {
+    fn write_children(
+        node: &FileNode,
+        out: &mut BufWriter<File>,
+        position: &mut u64,
+        dirs: &mut HashMap<String, DirSpan>,
+    ) -> Result<(), RepoError> {
+        let children = node.children.as_deref().unwrap_or_default();
+        let start = *position;
+        let mut offsets = vec![];
+        for (i, child) in children.iter().enumerate() {
+            if i % OFFSET_STRIDE == 0 {
+                offsets.push(*position);
+            }
+            let mut line = serde_json::to_vec(&without_children(child))?;
+            line.push(b'\n');
+            out.write_all(&line)?;
+            *position += line.len() as u64;
+        }
+        dirs.insert(
+            node.path.clone(),
+            DirSpan {
+                start,
+                end: *position,
+                count: children.len(),
+                offsets,
+            },
+        );
+
+        for child in children.iter().filter(|child| child.children.is_some()) {
+            write_children(child, out, position, dirs)?;
+        }
+        Ok(())
+    }
+
+    let meta_dir = repo_dir.join("_meta");
+    fs::create_dir_all(&meta_dir)?;
+    let generation = format!(
+        "{}-{}",
+        std::process::id(),
+        chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    );
+
+    // Both files are written aside and renamed over, the index last
+    let data_tmp = meta_dir.join(format!("{}.tmp", TREE_DATA_FILE));
+    let mut out = BufWriter::new(File::create(&data_tmp)?);
+    let mut header = serde_json::to_vec(&DataHeader {
+        generation: generation.clone(),
+    })?;
+    header.push(b'\n');
+    out.write_all(&header)?;
+    let mut dirs = HashMap::new();
+    write_children(tree, &mut out, &mut (header.len() as u64), &mut dirs)?;
+    out.flush()?;
+    drop(out);
+
+    let index = TreeIndex {
+        generation: Some(generation),
+        root: without_children(tree),
+        dirs,
+    };
+    let index_tmp = meta_dir.join(format!("{}.tmp", TREE_INDEX_FILE));
+    fs::write(&index_tmp, serde_json::to_vec(&index)?)?;
+
+    fs::rename(&data_tmp, data_path(repo_dir))?;
+    fs::rename(&index_tmp, index_path(repo_dir))?;
+    let _ = fs::remove_file(meta_dir.join(LEGACY_TREE_FILE));
+    Ok(())
+}
+
+/// Parsed indexes by repo dir
+type TreeIndexCache = HashMap<PathBuf, Arc<TreeIndex>>;
+
+/// Indexes are reparsed only when the data file is from another generation
+fn tree_indexes() -> &'static Mutex<TreeIndexCache> {
+    static INDEXES: OnceLock<Mutex<TreeIndexCache>> = OnceLock::new();
+    INDEXES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Generation named by the data file's header line, if it has one
+fn read_generation(file: &mut File) -> Result<Option<String>, RepoError> {
+    let mut line = vec![];
+    BufReader::new(Read::take(&mut *file, MAX_HEADER_SIZE)).read_until(b'\n', &mut line)?;
+    Ok(serde_json::from_slice::<DataHeader>(&line).ok().map(|header| header.generation))
+}
+
+/// The index and an open data file from the same write. A reader between the
+/// writer's two renames sees them disagree, so it waits for the second.
+fn open_tree(repo_dir: &Path) -> Result<(Arc<TreeIndex>, File), RepoError> {
+    for _ in 0..OPEN_ATTEMPTS {
+        let mut file = File::open(data_path(repo_dir))?;
+        let generation = read_generation(&mut file)?;
+
+        let cached = tree_indexes().lock().unwrap().get(repo_dir).cloned();
+        if let Some(index) = cached.filter(|index| index.generation == generation) {
+            return Ok((index, file));
+        }
+        let index: Arc<TreeIndex> = Arc::new(serde_json::from_slice(&fs::read(index_path(repo_dir))?)?);
+        if index.generation == generation {
+            tree_indexes().lock().unwrap().insert(repo_dir.to_path_buf(), index.clone());
+            return Ok((index, file));
+        }
+        std::thread::sleep(OPEN_RETRY_DELAY);
+    }
+    Err(RepoError::IoError(io::Error::other(format!(
+        "Tree index doesn't match its data: {}",
+        repo_dir.display()
+    ))))
+}
+
+fn read_all(mut file: File) -> Result<Vec<u8>, RepoError> {
+    let mut data = vec![];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// The root node, with `child_count` but no children
+pub fn read_root(repo_dir: &Path) -> Result<FileNode, RepoError> {
+    Ok(open_tree(repo_dir)?.0.root.clone())
+}
+
+/// Up to `limit` children of the folder at `path`, starting at `offset`
+pub fn read_children(repo_dir: &Path, path: &str, offset: usize, limit: usize) -> Result<TreePage, RepoError> {
+    let (index, mut file) = open_tree(repo_dir)?;
+    let span = index.dirs.get(path.trim_matches('/')).ok_or_else(|| {
+        RepoError::IoError(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Folder not in tree: {}", path),
+        ))
+    })?;
+
+    // Starts at the nearest recorded line before `offset`
+    let (start, skip) = match span.offsets.get(offset / OFFSET_STRIDE) {
+        Some(&start) => (start, offset % OFFSET_STRIDE),
+        None if span.offsets.is_empty() => (span.start, offset),
+        None => (span.end, 0),
+    };
+    file.seek(SeekFrom::Start(start))?;
+    let mut reader = BufReader::new(file.take(span.end.saturating_sub(start)));
+
+    let mut children = vec![];
+    let mut line = vec![];
+    let mut seen = 0;
+    while children.len() < limit {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        if line.trim_ascii().is_empty() {
+            continue;
+        }
+        if seen >= skip {
+            children.push(serde_json::from_slice(&line)?);
+        }
+        seen += 1;
+    }
+
+    Ok(TreePage {
+        path: path.trim_matches('/').to_string(),
+        offset,
+        total: span.count,
+        children,
+    })
+}
+
+/// The whole tree with every folder's children filled in
+pub fn read_tree(repo_dir: &Path) -> Result<FileNode, RepoError> {
+    fn fill(node: &mut FileNode, index: &TreeIndex, data: &[u8]) -> Result<(), RepoError> {
+        let Some(span) = index.dirs.get(&node.path) else {
+            return Ok(());
+        };
+        let mut children = data[span.start as usize..span.end as usize]
+            .split(|&b| b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(serde_json::from_slice)
+            .collect::<Result<Vec<FileNode>, _>>()?;
+        for child in children.iter_mut().filter(|child| child.is_dir) {
+            fill(child, index, data)?;
+        }
+        node.children = Some(children);
+        Ok(())
+    }
+
+    let (index, file) = open_tree(repo_dir)?;
+    let data = read_all(file)?;
+    let mut root = index.root.clone();
+    fill(&mut root, &index, &data)?;
+    Ok(root)
+}
+
+/// Paths of every file in the tree, without reading the folders into nodes
+pub fn read_file_paths(repo_dir: &Path) -> Result<Vec<String>, RepoError> {
+    #[derive(Deserialize)]
+    struct Entry {
+        path: String,
+        is_dir: bool,
+    }
+
+    let (index, file) = open_tree(repo_dir)?;
+    let data = read_all(file)?;
+    let mut lines = data.split(|&b| b == b'\n');
+    if index.generation.is_some() {
+        lines.next();
+    }
+
+    let mut paths = vec![];
+    for line in lines.filter(|line| !line.is_empty()) {
+        let entry: Entry = serde_json::from_slice(line)?;
+        if !entry.is_dir {
+            paths.push(entry.path);
+        }
+    }
+    Ok(paths)
+}
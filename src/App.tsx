@@ -1,6 +1,6 @@
-import { useState, useEffect, useCallback, useRef } from "react";
+import { useState, useEffect, useCallback, useMemo, useRef } from "react";
 import { openUrl } from "@tauri-apps/plugin-opener";
-import { save } from "@tauri-apps/plugin-dialog";
+import { open, save } from "@tauri-apps/plugin-dialog";
 import * as Select from "@radix-ui/react-select";
 import { FileTree } from "./components/FileTree";
 import { CodeViewer, type CodeViewerHandle } from "./components/CodeViewer";
@@ -14,9 +14,16 @@ import { ResizableSidebar } from "./components/ResizableSidebar";
 import {
   importRepoFromGithub,
   readTextFile,
+  fetchLfsObject,
+  importAllFavorites,
+  onBatchImportProgress,
+  isLayoutMigrationRunning,
+  onLayoutMigrationFinished,
   listRecentRepos,
   getRepoTree,
+  listRepoFiles,
   deleteRepo,
+  refreshRepo,
   searchGithubRepos,
   getSettings,
   updateSettings,
@@ -30,16 +37,26 @@ import {
   addFileHistory,
   createGist,
   updateRepoLastOpened,
+  setRepoPinned,
+  setRepoIgnorePatterns,
+  getStorageUsage,
+  getStorageLocations,
+  moveStorage,
 } from "./api";
 import type {
   FileNode,
   RepoInfo,
+  RepoGroup,
+  BatchStatus,
   FileContent,
   SearchResultItem,
   AppSettings,
+  GitHubHost,
   TrendingRepo,
   FavoriteRepo,
   FileHistoryEntry,
+  StorageUsage,
+  StorageLocations,
 } from "./types";
 import "./App.css";
 
@@ -63,6 +80,12 @@ function formatStars(count: number): string {
   return String(count);
 }
 
+function formatBytes(bytes: number): string {
+  if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(0)} KB`;
+  if (bytes < 1024 * 1024 * 1024) return `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
+  return `${(bytes / (1024 * 1024 * 1024)).toFixed(2)} GB`;
+}
+
 function formatExportTimestamp(date: Date): string {
   const pad = (value: number) => String(value).padStart(2, "0");
   return `${date.getFullYear()}${pad(date.getMonth() + 1)}${pad(date.getDate())}-${pad(
@@ -70,13 +93,13 @@ function formatExportTimestamp(date: Date): string {
   )}${pad(date.getMinutes())}`;
 }
 
-// Check if input looks like a GitHub URL
-function isGitHubUrl(input: string): boolean {
+// Check if input looks like a repository URL (GitHub, GitLab, Gitea/Codeberg, Bitbucket)
+function isRepoUrl(input: string): boolean {
   const trimmed = input.trim().toLowerCase();
   return (
-    trimmed.startsWith("https://github.com/") ||
-    trimmed.startsWith("http://github.com/") ||
-    trimmed.startsWith("github.com/") ||
+    trimmed.startsWith("https://") ||
+    trimmed.startsWith("http://") ||
+    /^(www\.)?(github\.com|gitlab\.com|codeberg\.org|gitea\.com|bitbucket\.org)\//.test(trimmed) ||
     /^[\w-]+\/[\w.-]+$/.test(trimmed) // owner/repo format
   );
 }
@@ -265,6 +288,8 @@ function SettingsPage({
   favoritesCount,
   onExport,
   exporting,
+  onImportAll,
+  batchStatus,
 }: {
   settings: AppSettings;
   onSave: (settings: AppSettings) => Promise<void>;
@@ -272,6 +297,8 @@ function SettingsPage({
   favoritesCount: number;
   onExport: (format: "json" | "markdown") => Promise<void>;
   exporting: boolean;
+  onImportAll: () => Promise<void>;
+  batchStatus: BatchStatus | null;
 }) {
   const [token, setToken] = useState(settings.github_token || "");
   const [openrouterKey, setOpenrouterKey] = useState(settings.openrouter_api_key || "");
@@ -290,6 +317,59 @@ function SettingsPage({
   const [savedModel, setSavedModel] = useState(false);
   const [savingPrompt, setSavingPrompt] = useState(false);
   const [savedPrompt, setSavedPrompt] = useState(false);
+  const [proxyUrl, setProxyUrl] = useState(settings.proxy_url || "");
+  const [noProxy, setNoProxy] = useState(settings.no_proxy || "");
+  const [connectTimeout, setConnectTimeout] = useState(settings.connect_timeout_secs);
+  const [readTimeout, setReadTimeout] = useState(settings.read_timeout_secs);
+  const [savingNetwork, setSavingNetwork] = useState(false);
+  const [savedNetwork, setSavedNetwork] = useState(false);
+  const [networkError, setNetworkError] = useState("");
+  const [newHost, setNewHost] = useState<GitHubHost>({ host: "", api_url: "", archive_url: "", token: "" });
+  const [quotaMb, setQuotaMb] = useState(settings.cache_quota_mb?.toString() ?? "");
+  const [savingQuota, setSavingQuota] = useState(false);
+  const [savedQuota, setSavedQuota] = useState(false);
+  const [storageUsage, setStorageUsage] = useState<StorageUsage | null>(null);
+  const [ignorePatterns, setIgnorePatterns] = useState(settings.ignore_patterns.join("\n"));
+  const [savingIgnore, setSavingIgnore] = useState(false);
+  const [savedIgnore, setSavedIgnore] = useState(false);
+
+  const loadStorageUsage = useCallback(async () => {
+    try {
+      setStorageUsage(await getStorageUsage());
+    } catch (err) {
+      console.error("Failed to load storage usage:", err);
+    }
+  }, []);
+
+  useEffect(() => {
+    loadStorageUsage();
+  }, [loadStorageUsage]);
+
+  const [locations, setLocations] = useState<StorageLocations | null>(null);
+  const [movingStorage, setMovingStorage] = useState(false);
+  const [moveError, setMoveError] = useState("");
+
+  useEffect(() => {
+    getStorageLocations().then(setLocations).catch((err) => console.error("Failed to load storage locations:", err));
+  }, []);
+
+  const handleMoveStorage = async (dataDir: string | null, configDir: string | null, portable: boolean) => {
+    setMoveError("");
+    setMovingStorage(true);
+    try {
+      setLocations(await moveStorage(dataDir, configDir, portable));
+      await loadStorageUsage();
+    } catch (err) {
+      setMoveError(String(err));
+    } finally {
+      setMovingStorage(false);
+    }
+  };
+
+  const pickFolder = async () => {
+    const selected = await open({ directory: true });
+    return typeof selected === "string" ? selected : null;
+  };
 
   const saveSettings = async (updates: Partial<AppSettings>, setSaving: (v: boolean) => void, setSaved: (v: boolean) => void) => {
     setSaving(true);
@@ -303,7 +383,7 @@ function SettingsPage({
   };
 
   const handleOpenGitHub = async () => {
-    await openUrl("https://github.com/settings/tokens/new?description=RepoRead&scopes=public_repo,gist");
+    await openUrl("https://github.com/settings/tokens/new?description=RepoRead&scopes=repo,gist");
   };
 
   const hasFavorites = favoritesCount > 0;
@@ -321,7 +401,7 @@ function SettingsPage({
         <section className="settings-section">
           <h2>GitHub Token</h2>
           <p className="settings-desc">
-            Add a personal access token to increase API rate limits.
+            Add a personal access token to increase API rate limits and import private repositories.
           </p>
           <div className="settings-rate-info">
             <span className="rate-item">Without token: <strong>10 searches/min</strong></span>
@@ -346,13 +426,87 @@ function SettingsPage({
           </div>
         </section>
 
+        <section className="settings-section">
+          <h2>GitHub Enterprise</h2>
+          <p className="settings-desc">
+            Repositories on these hosts are treated as GitHub and use their own token. The API URL defaults to{" "}
+            <code>https://&lt;host&gt;/api/v3</code>.
+          </p>
+          {settings.github_hosts.length > 0 && (
+            <ul className="github-host-list">
+              {settings.github_hosts.map((h) => (
+                <li key={h.host}>
+                  <span>{h.host}</span>
+                  <span className="github-host-api">{h.api_url || `https://${h.host}/api/v3`}</span>
+                  <button
+                    className="github-link-button"
+                    onClick={() => onSave({ ...settings, github_hosts: settings.github_hosts.filter((x) => x.host !== h.host) })}
+                  >
+                    Remove
+                  </button>
+                </li>
+              ))}
+            </ul>
+          )}
+          <div className="github-host-form">
+            <input
+              type="text"
+              className="token-input"
+              placeholder="github.example.com"
+              value={newHost.host}
+              onChange={(e) => setNewHost({ ...newHost, host: e.target.value })}
+            />
+            <input
+              type="text"
+              className="token-input"
+              placeholder="API URL (optional)"
+              value={newHost.api_url || ""}
+              onChange={(e) => setNewHost({ ...newHost, api_url: e.target.value })}
+            />
+            <input
+              type="text"
+              className="token-input"
+              placeholder="Archive URL (optional)"
+              value={newHost.archive_url || ""}
+              onChange={(e) => setNewHost({ ...newHost, archive_url: e.target.value })}
+            />
+            <input
+              type="password"
+              className="token-input"
+              placeholder="Token"
+              value={newHost.token || ""}
+              onChange={(e) => setNewHost({ ...newHost, token: e.target.value })}
+            />
+            <button
+              className="save-button"
+              disabled={!newHost.host.trim()}
+              onClick={async () => {
+                const host = newHost.host.trim().toLowerCase();
+                const entry: GitHubHost = {
+                  host,
+                  api_url: newHost.api_url?.trim() || null,
+                  archive_url: newHost.archive_url?.trim() || null,
+                  token: newHost.token || null,
+                };
+                await onSave({
+                  ...settings,
+                  github_hosts: [...settings.github_hosts.filter((h) => h.host !== host), entry],
+                });
+                setNewHost({ host: "", api_url: "", archive_url: "", token: "" });
+              }}
+            >
+              Add Host
+            </button>
+          </div>
+        </section>
+
         <section className="settings-section">
           <h2>How to get a GitHub Token</h2>
           <ol className="token-steps">
             <li>Click the button below to open GitHub token settings</li>
             <li>Sign in to GitHub if prompted</li>
             <li>Set expiration (recommend: 90 days or longer)</li>
-            <li>Under "Select scopes", check <code>public_repo</code> & <code>gist</code></li>
+            <li>Under "Select scopes", check <code>repo</code> & <code>gist</code></li>
             <li>Click "Generate token" at the bottom</li>
             <li>Copy the token and paste it above</li>
           </ol>
@@ -494,6 +648,333 @@ function SettingsPage({
           </div>
         </section>
 
+        <section className="settings-section">
+          <h2>Import Favorites</h2>
+          <p className="settings-desc">
+            Download every favorite in the background, {settings.max_concurrent_imports} at a time.
+          </p>
+          <button
+            className="save-button"
+            onClick={onImportAll}
+            disabled={!hasFavorites || (batchStatus !== null && !batchStatus.finished)}
+          >
+            Import All Favorites
+          </button>
+          {batchStatus && (
+            <p className="settings-desc" style={{ marginTop: 12 }}>
+              {batchStatus.items.filter((item) => item.state === "done").length} of {batchStatus.items.length} imported
+              {batchStatus.items.some((item) => item.state === "failed") &&
+                `, ${batchStatus.items.filter((item) => item.state === "failed").length} failed`}
+              {batchStatus.finished ? "" : "..."}
+            </p>
+          )}
+        </section>
+
+        <section className="settings-section">
+          <h2>Importing</h2>
+          <p className="settings-desc">
+            Repository archives leave submodule folders empty. Fetching them downloads each pinned commit too.
+          </p>
+          <div className="settings-toggle-row">
+            <label className="settings-toggle-label">
+              <span>Fetch submodules</span>
+              <input
+                type="checkbox"
+                className="settings-toggle"
+                checked={settings.fetch_submodules}
+                onChange={async (e) => {
+                  const newSettings = { ...settings, fetch_submodules: e.target.checked };
+                  await onSave(newSettings);
+                }}
+              />
+            </label>
+          </div>
+          <p className="settings-desc" style={{ marginTop: 20 }}>
+            Keeping the downloaded archive saves disk space and import time. Files are read from it when opened.
+            Submodules aren't fetched for repos stored this way.
+          </p>
+          <div className="settings-toggle-row">
+            <label className="settings-toggle-label">
+              <span>Keep archives instead of extracting</span>
+              <input
+                type="checkbox"
+                className="settings-toggle"
+                checked={settings.storage_mode === "archive"}
+                onChange={async (e) => {
+                  const newSettings: AppSettings = {
+                    ...settings,
+                    storage_mode: e.target.checked ? "archive" : "extracted",
+                  };
+                  await onSave(newSettings);
+                }}
+              />
+            </label>
+          </div>
+          <p className="settings-desc" style={{ marginTop: 20 }}>
+            Store each file's contents once, shared by every repository and branch that has it. Saves space when
+            keeping many refs of the same repository.
+          </p>
+          <div className="settings-toggle-row">
+            <label className="settings-toggle-label">
+              <span>Deduplicate files across repositories</span>
+              <input
+                type="checkbox"
+                className="settings-toggle"
+                checked={settings.storage_mode === "deduplicated"}
+                onChange={async (e) => {
+                  const newSettings: AppSettings = {
+                    ...settings,
+                    storage_mode: e.target.checked ? "deduplicated" : "extracted",
+                  };
+                  await onSave(newSettings);
+                }}
+              />
+            </label>
+          </div>
+        </section>
+
+        <section className="settings-section">
+          <h2>File Tree</h2>
+          <p className="settings-desc">
+            Paths matching these patterns are hidden from every repository's file tree, one pattern per line in
+            .gitignore syntax. Patterns set on a repository from its menu take precedence.
+          </p>
+          <textarea
+            className="prompt-textarea"
+            value={ignorePatterns}
+            onChange={(e) => setIgnorePatterns(e.target.value)}
+            rows={5}
+            placeholder="node_modules/"
+          />
+          <button
+            className="save-button"
+            onClick={() =>
+              saveSettings(
+                {
+                  ignore_patterns: ignorePatterns
+                    .split("\n")
+                    .map((line) => line.trim())
+                    .filter(Boolean),
+                },
+                setSavingIgnore,
+                setSavedIgnore
+              )
+            }
+            disabled={savingIgnore}
+          >
+            {savingIgnore ? "Saving..." : savedIgnore ? "Saved ✓" : "Save"}
+          </button>
+          <div className="settings-toggle-row">
+            <label className="settings-toggle-label">
+              <span>Hide files ignored by the repository's .gitignore</span>
+              <input
+                type="checkbox"
+                className="settings-toggle"
+                checked={settings.respect_gitignore}
+                onChange={async (e) => {
+                  await onSave({ ...settings, respect_gitignore: e.target.checked });
+                }}
+              />
+            </label>
+          </div>
+          <div className="settings-toggle-row">
+            <label className="settings-toggle-label">
+              <span>Hide vendored code marked in .gitattributes</span>
+              <input
+                type="checkbox"
+                className="settings-toggle"
+                checked={settings.hide_vendored}
+                onChange={async (e) => {
+                  await onSave({ ...settings, hide_vendored: e.target.checked });
+                }}
+              />
+            </label>
+          </div>
+        </section>
+
+        <section className="settings-section">
+          <h2>Storage</h2>
+          <p className="settings-desc">
+            Limit the disk space used by downloaded repositories. Beyond it, the least recently opened are deleted.
+            Favorites and pinned repositories are always kept. Leave empty for no limit.
+          </p>
+          <div className="github-host-form">
+            <label className="settings-desc">
+              Limit (MB)
+              <input
+                type="number"
+                className="token-input"
+                min={1}
+                placeholder="No limit"
+                value={quotaMb}
+                onChange={(e) => setQuotaMb(e.target.value)}
+              />
+            </label>
+            <button
+              className="save-button"
+              onClick={async () => {
+                const mb = Number(quotaMb);
+                await saveSettings(
+                  { cache_quota_mb: quotaMb.trim() && mb > 0 ? Math.floor(mb) : null },
+                  setSavingQuota,
+                  setSavedQuota
+                );
+                await loadStorageUsage();
+              }}
+              disabled={savingQuota}
+            >
+              {savingQuota ? "Saving..." : savedQuota ? "Saved ✓" : "Save"}
+            </button>
+          </div>
+          {storageUsage && (
+            <>
+              <p className="settings-desc" style={{ marginTop: 20 }}>
+                Using {formatBytes(storageUsage.total_bytes)}
+                {storageUsage.quota_bytes !== null && ` of ${formatBytes(storageUsage.quota_bytes)}`}
+                {storageUsage.blob_store_bytes > 0 &&
+                  `, ${formatBytes(storageUsage.blob_store_bytes)} of it in shared file storage`}
+              </p>
+              <ul className="storage-usage-list">
+                {storageUsage.repos.map((r) => (
+                  <li key={r.key}>
+                    <span>
+                      {r.owner}/{r.repo}
+                      {r.branch && ` @ ${r.branch}`}
+                      {r.pinned && " · pinned"}
+                      {r.favorite && " · ★"}
+                    </span>
+                    <span className="storage-usage-size">
+                      {formatBytes(r.size_bytes)}
+                      {r.shared_bytes > 0 && ` + ${formatBytes(r.shared_bytes)} shared`}
+                    </span>
+                  </li>
+                ))}
+              </ul>
+            </>
+          )}
+          {locations && (
+            <>
+              <p className="settings-desc" style={{ marginTop: 20 }}>
+                Repositories and screenshots are kept in <code>{locations.data_dir}</code>, settings and history in{" "}
+                <code>{locations.config_dir}</code>. Moving them copies everything, checks the copy, then removes the
+                originals.
+              </p>
+              <div className="settings-toggle-row">
+                <label className="settings-toggle-label">
+                  <span>Portable mode (keep everything next to the app)</span>
+                  <input
+                    type="checkbox"
+                    className="settings-toggle"
+                    checked={locations.portable}
+                    disabled={movingStorage}
+                    onChange={(e) => handleMoveStorage(null, null, e.target.checked)}
+                  />
+                </label>
+              </div>
+              {!locations.portable && (
+                <div className="export-buttons">
+                  <button
+                    className="github-link-button"
+                    disabled={movingStorage}
+                    onClick={async () => {
+                      const dir = await pickFolder();
+                      if (dir) await handleMoveStorage(dir, locations.config_dir, false);
+                    }}
+                  >
+                    Move Data...
+                  </button>
+                  <button
+                    className="github-link-button"
+                    disabled={movingStorage}
+                    onClick={async () => {
+                      const dir = await pickFolder();
+                      if (dir) await handleMoveStorage(locations.data_dir, dir, false);
+                    }}
+                  >
+                    Move Settings...
+                  </button>
+                  <button
+                    className="github-link-button"
+                    disabled={movingStorage}
+                    onClick={() => handleMoveStorage(null, null, false)}
+                  >
+                    Use Default Folders
+                  </button>
+                </div>
+              )}
+              {movingStorage && <p className="settings-desc">Moving files...</p>}
+              {moveError && <p className="settings-desc">{moveError}</p>}
+            </>
+          )}
+        </section>
+
+        <section className="settings-section">
+          <h2>Network</h2>
+          <p className="settings-desc">
+            Route all requests through a proxy. Leave empty to use the <code>HTTPS_PROXY</code> environment variable.
+          </p>
+          <div className="github-host-form">
+            <input
+              type="text"
+              className="token-input"
+              placeholder="http://proxy.example.com:8080"
+              value={proxyUrl}
+              onChange={(e) => setProxyUrl(e.target.value)}
+            />
+            <input
+              type="text"
+              className="token-input"
+              placeholder="Hosts that bypass the proxy, comma-separated"
+              value={noProxy}
+              onChange={(e) => setNoProxy(e.target.value)}
+            />
+            <label className="settings-desc">
+              Connect timeout (seconds)
+              <input
+                type="number"
+                className="token-input"
+                min={1}
+                value={connectTimeout}
+                onChange={(e) => setConnectTimeout(Number(e.target.value))}
+              />
+            </label>
+            <label className="settings-desc">
+              Read timeout (seconds)
+              <input
+                type="number"
+                className="token-input"
+                min={1}
+                value={readTimeout}
+                onChange={(e) => setReadTimeout(Number(e.target.value))}
+              />
+            </label>
+            <button
+              className="save-button"
+              onClick={async () => {
+                setNetworkError("");
+                try {
+                  await saveSettings(
+                    {
+                      proxy_url: proxyUrl.trim() || null,
+                      no_proxy: noProxy.trim() || null,
+                      connect_timeout_secs: Math.max(1, connectTimeout || 15),
+                      read_timeout_secs: Math.max(1, readTimeout || 60),
+                    },
+                    setSavingNetwork,
+                    setSavedNetwork
+                  );
+                } catch (err) {
+                  setNetworkError(String(err));
+                }
+              }}
+              disabled={savingNetwork}
+            >
+              {savingNetwork ? "Saving..." : savedNetwork ? "Saved ✓" : "Save"}
+            </button>
+            {networkError && <p className="settings-desc">{networkError}</p>}
+          </div>
+        </section>
+
         <section className="settings-section">
           <h2>Screenshots</h2>
           <p className="settings-desc">
@@ -538,9 +1019,13 @@ function SettingsPage({
 function App() {
   const [view, setView] = useState<View>("home");
   const [homeTab, setHomeTab] = useState<HomeTab>("home");
-  const [recentRepos, setRecentRepos] = useState<RepoInfo[]>([]);
+  const [repoGroups, setRepoGroups] = useState<RepoGroup[]>([]);
+  const recentRepos = useMemo(() => repoGroups.flatMap((group) => group.refs), [repoGroups]);
   const [currentRepo, setCurrentRepo] = useState<RepoInfo | null>(null);
   const [tree, setTree] = useState<FileNode | null>(null);
+  // Every file path of the open repo, fetched the first time search or filtering needs it
+  const [repoFiles, setRepoFiles] = useState<string[] | null>(null);
+  const repoFilesRequestRef = useRef<FileNode | null>(null);
   const [selectedPath, setSelectedPath] = useState<string>("");
   const [revealRequestId, setRevealRequestId] = useState(0);
   const [fileContent, setFileContent] = useState<FileContent | null>(null);
@@ -555,6 +1040,7 @@ function App() {
   const [input, setInput] = useState("");
   const [isLoading, setIsLoading] = useState(false);
   const [searchResults, setSearchResults] = useState<SearchResultItem[]>([]);
+  const [searchStaleAt, setSearchStaleAt] = useState<string | null>(null);
   const [importingRepo, setImportingRepo] = useState<string | null>(null);
   const [updatingRepoKey, setUpdatingRepoKey] = useState<string | null>(null);
 
@@ -565,6 +1051,20 @@ function App() {
     openrouter_api_key: null,
     interpret_prompt: "This is {language} code from the {project} project. Please interpret the following code in under 500 words:\n\n```{language}\n{code}\n```",
     interpret_model: "anthropic/claude-sonnet-4",
+    max_extract_size_mb: 8192,
+    max_extract_entries: 1000000,
+    fetch_submodules: false,
+    max_concurrent_imports: 3,
+    github_hosts: [],
+    proxy_url: null,
+    no_proxy: null,
+    connect_timeout_secs: 15,
+    read_timeout_secs: 60,
+    storage_mode: "extracted",
+    cache_quota_mb: null,
+    ignore_patterns: [".git/", "node_modules/", "__pycache__/", ".DS_Store"],
+    respect_gitignore: true,
+    hide_vendored: true,
   });
 
   // Trending
@@ -573,6 +1073,8 @@ function App() {
   const [trendingLanguage, setTrendingLanguage] = useState("");
   const [trendingLoading, setTrendingLoading] = useState(false);
   const [trendingError, setTrendingError] = useState("");
+  // Set to the fetch time when trending was served from the cache while offline
+  const [trendingStaleAt, setTrendingStaleAt] = useState<string | null>(null);
   const [importingTrending, setImportingTrending] = useState<string | null>(null);
 
   // Top 20 popular programming languages
@@ -606,6 +1108,8 @@ function App() {
 
   // Toast
   const [toastMessage, setToastMessage] = useState<string | null>(null);
+  const [batchStatus, setBatchStatus] = useState<BatchStatus | null>(null);
+  const [migratingLayout, setMigratingLayout] = useState(false);
 
   // Screenshot
   const [isCapturing, setIsCapturing] = useState(false);
@@ -678,6 +1182,33 @@ function App() {
     return () => document.removeEventListener("keydown", handleKeyDown);
   }, [view]);
 
+  // Track background batch imports
+  useEffect(() => {
+    const unlisten = onBatchImportProgress((status) => {
+      setBatchStatus(status);
+      if (status.finished) {
+        loadRecentRepos();
+      }
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // Cached repos are unreadable while an upgrade moves them to the new layout
+  useEffect(() => {
+    const unlisten = onLayoutMigrationFinished(() => {
+      setMigratingLayout(false);
+      loadRecentRepos();
+    });
+    isLayoutMigrationRunning()
+      .then(setMigratingLayout)
+      .catch((err) => console.error("Failed to check layout migration:", err));
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
   // Load file history when repo changes
   useEffect(() => {
     if (currentRepo?.url) {
@@ -709,8 +1240,8 @@ function App() {
 
   const loadRecentRepos = async () => {
     try {
-      const repos = await listRecentRepos();
-      setRecentRepos(repos);
+      const groups = await listRecentRepos();
+      setRepoGroups(groups);
     } catch (err) {
       console.error("Failed to load recent repos:", err);
     }
@@ -744,7 +1275,8 @@ function App() {
         trendingSince,
         null
       );
-      setTrendingItems(items);
+      setTrendingItems(items.data);
+      setTrendingStaleAt(items.stale ? items.fetched_at : null);
     } catch (err) {
       setTrendingError(String(err));
     } finally {
@@ -760,9 +1292,9 @@ function App() {
     setError("");
 
     try {
-      if (isGitHubUrl(input)) {
+      if (isRepoUrl(input)) {
         // Direct import
-        const url = input.includes("github.com") ? input : `https://github.com/${input}`;
+        const url = /^[\w-]+\/[\w.-]+$/.test(input.trim()) ? `https://github.com/${input.trim()}` : input;
         const result = await importRepoFromGithub(url);
         setCurrentRepo(result.info);
         setTree(result.tree);
@@ -775,7 +1307,8 @@ function App() {
       } else {
         // Search
         const results = await searchGithubRepos(input, settings.github_token);
-        setSearchResults(results);
+        setSearchResults(results.data);
+        setSearchStaleAt(results.stale ? results.fetched_at : null);
       }
     } catch (err) {
       setError(String(err));
@@ -876,18 +1409,45 @@ function App() {
     }
   };
 
+  const handleRepoTogglePin = async (repo: RepoInfo) => {
+    try {
+      await setRepoPinned(repo.key, !repo.pinned);
+      await loadRecentRepos();
+    } catch (err) {
+      setError(String(err));
+    }
+  };
+
+  const handleRepoEditIgnore = async (repo: RepoInfo) => {
+    const input = prompt(
+      "Ignore patterns for this repository, separated by commas. Prefix a pattern with ! to show what the global patterns hide.",
+      (repo.ignore_patterns ?? []).join(", ")
+    );
+    if (input === null) return;
+
+    try {
+      const repoTree = await setRepoIgnorePatterns(repo.key, input.split(","));
+      if (currentRepo?.key === repo.key) {
+        setTree(repoTree);
+      }
+      await loadRecentRepos();
+    } catch (err) {
+      setError(String(err));
+    }
+  };
+
   const handleRepoUpdate = async (repoKey: string) => {
     if (updatingRepoKey) return;
     const repo = recentRepos.find((item) => item.key === repoKey);
     if (!repo) return;
-    if (!confirm(`Update ${repo.owner}/${repo.repo}? This will delete the local copy and download the latest version.`)) {
+    const label = repo.branch ? `${repo.owner}/${repo.repo}@${repo.branch}` : `${repo.owner}/${repo.repo}`;
+    if (!confirm(`Update ${label}? The local copy is replaced once the latest version has downloaded.`)) {
       return;
     }
 
     try {
       setUpdatingRepoKey(repoKey);
-      await deleteRepo(repoKey);
-      const result = await importRepoFromGithub(repo.url);
+      const result = await refreshRepo(repoKey);
       await loadRecentRepos();
 
       if (currentRepo?.key === repoKey) {
@@ -903,6 +1463,24 @@ function App() {
     }
   };
 
+  const handleImportAllFavorites = async () => {
+    try {
+      setBatchStatus(await importAllFavorites());
+    } catch (err) {
+      setToastMessage(String(err));
+    }
+  };
+
+  const handleFetchLfs = async () => {
+    if (!currentRepo || !selectedPath) return;
+    try {
+      const content = await fetchLfsObject(currentRepo.key, selectedPath);
+      setFileContent(content);
+    } catch (err) {
+      setToastMessage(String(err));
+    }
+  };
+
   const openFile = useCallback(
     async (path: string, recordHistory = true) => {
       if (!currentRepo || !path) return;
@@ -964,6 +1542,32 @@ function App() {
     [currentRepo]
   );
 
+  useEffect(() => {
+    setRepoFiles(null);
+    repoFilesRequestRef.current = null;
+  }, [tree]);
+
+  const loadRepoFiles = useCallback(async () => {
+    if (!currentRepo || !tree || repoFilesRequestRef.current === tree) return;
+    repoFilesRequestRef.current = tree;
+    try {
+      const files = await listRepoFiles(currentRepo.key);
+      // Dropped if another tree was loaded meanwhile
+      if (repoFilesRequestRef.current === tree) {
+        setRepoFiles(files);
+      }
+    } catch (err) {
+      console.error("Failed to list repository files:", err);
+      repoFilesRequestRef.current = null;
+    }
+  }, [currentRepo, tree]);
+
+  useEffect(() => {
+    if ((fileSearchOpen || contentSearchOpen) && !repoFiles) {
+      loadRepoFiles();
+    }
+  }, [fileSearchOpen, contentSearchOpen, repoFiles, loadRepoFiles]);
+
   const handleRevealInTree = useCallback(() => {
     if (!selectedPath) return;
     setRevealRequestId((prev) => prev + 1);
@@ -998,6 +1602,12 @@ function App() {
   const handleSaveSettings = async (newSettings: AppSettings) => {
     await updateSettings(newSettings);
     setSettings(newSettings);
+    // A lowered quota may have evicted repos
+    await loadRecentRepos();
+    // Changed ignore rules rebuild every tree
+    if (currentRepo) {
+      setTree(await getRepoTree(currentRepo.key));
+    }
   };
 
   const handleInterpret = async () => {
@@ -1039,7 +1649,13 @@ function App() {
   };
 
   const handleCreateGist = async () => {
-    if (!settings.github_token) {
+    // Snippets from an Enterprise instance are shared there, with that instance's token
+    const gistHost =
+      currentRepo?.provider === "github" && currentRepo.host !== "github.com" ? currentRepo.host : null;
+    const gistToken = gistHost
+      ? settings.github_hosts.find((h) => h.host === gistHost)?.token || ""
+      : settings.github_token;
+    if (!gistToken) {
       setToastMessage("Please set GitHub token in Settings first");
       return;
     }
@@ -1058,11 +1674,12 @@ function App() {
       const description = `Code snippet from ${project}`;
 
       const result = await createGist(
-        settings.github_token,
+        gistToken,
         filename,
         selectedText,
         description,
-        false // private gist
+        false, // private gist
+        gistHost
       );
 
       // Open the gist URL in browser
@@ -1158,13 +1775,15 @@ function App() {
         favoritesCount={favorites.length}
         onExport={handleExportFavorites}
         exporting={exporting}
+        onImportAll={handleImportAllFavorites}
+        batchStatus={batchStatus}
       />
     );
   }
 
   // Home view
   if (view === "home") {
-    const inputHint = isGitHubUrl(input)
+    const inputHint = isRepoUrl(input)
       ? "Press Enter to import this repository"
       : input.trim()
       ? "Press Enter to search GitHub"
@@ -1196,7 +1815,7 @@ function App() {
                   {isLoading ? (
                     <>
                       <span className="spinner small"></span>
-                      {isGitHubUrl(input) ? "Importing..." : "Searching..."}
+                      {isRepoUrl(input) ? "Importing..." : "Searching..."}
                     </>
                   ) : (
                     "Go"
@@ -1205,6 +1824,9 @@ function App() {
               </form>
               {inputHint && <p className="input-hint">{inputHint}</p>}
               {error && <p className="input-error">{error}</p>}
+              {searchStaleAt && searchResults.length > 0 && (
+                <p className="stale-notice">Offline: showing results from {new Date(searchStaleAt).toLocaleString()}</p>
+              )}
             </div>
 
             {/* Search Results */}
@@ -1215,11 +1837,16 @@ function App() {
             />
 
             {/* Recent Repos */}
+            {migratingLayout && (
+              <p className="stale-notice">Updating cached repositories to the new storage format...</p>
+            )}
             <RepoList
-              repos={recentRepos}
+              groups={repoGroups}
               onSelect={handleRepoSelect}
               onDelete={handleRepoDelete}
               onUpdate={handleRepoUpdate}
+              onTogglePin={handleRepoTogglePin}
+              onEditIgnore={handleRepoEditIgnore}
               updatingRepoKey={updatingRepoKey}
             />
           </>
@@ -1306,6 +1933,9 @@ function App() {
               </div>
             </div>
             {trendingError && <p className="input-error">{trendingError}</p>}
+            {trendingStaleAt && (
+              <p className="stale-notice">Offline: showing trending from {new Date(trendingStaleAt).toLocaleString()}</p>
+            )}
             {trendingLoading ? (
               <p className="loading-state">Loading trending repositories...</p>
             ) : (
@@ -1465,9 +2095,12 @@ function App() {
 
       <div className="repo-content">
         <ResizableSidebar>
-          {tree && (
+          {tree && currentRepo && (
             <FileTree
+              repoKey={currentRepo.key}
               tree={tree}
+              files={repoFiles}
+              onRequestFiles={loadRepoFiles}
               onFileSelect={handleFileSelect}
               selectedPath={selectedPath}
               revealPath={selectedPath}
@@ -1490,6 +2123,7 @@ function App() {
             isLoading={isLoadingFile}
             repoInfo={currentRepo}
             onRevealInTree={handleRevealInTree}
+            onFetchLfs={handleFetchLfs}
             revealLine={pendingReveal?.path === selectedPath ? pendingReveal.line : undefined}
             onRevealComplete={() => setPendingReveal(null)}
             copyScreenshotToClipboard={settings.copy_screenshot_to_clipboard}
@@ -1537,14 +2171,14 @@ function App() {
       <FileSearch
         isOpen={fileSearchOpen}
         onClose={() => setFileSearchOpen(false)}
-        tree={tree}
+        files={repoFiles}
         onFileSelect={handleFileSelect}
       />
 
       <ContentSearch
         isOpen={contentSearchOpen}
         onClose={() => setContentSearchOpen(false)}
-        tree={tree}
+        files={repoFiles}
         readFileContent={readFileContentForSearch}
         onResultSelect={handleContentResultSelect}
       />
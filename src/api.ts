@@ -1,7 +1,10 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 import type {
   FileNode,
+  TreePage,
   RepoInfo,
+  RepoGroup,
   ImportResult,
   FileContent,
   SearchResultItem,
@@ -12,24 +15,101 @@ import type {
   CreateGistResult,
   ChatSession,
   ChatSessionSummary,
+  DownloadProgress,
+  ProviderKind,
+  RepoUpdateStatus,
+  BatchStatus,
+  RefreshResult,
+  Cached,
+  StorageUsage,
+  StorageLocations,
 } from "./types";
 
 export async function importRepoFromGithub(url: string): Promise<ImportResult> {
   return invoke<ImportResult>("import_repo_from_github", { url });
 }
 
+export async function importLocalArchive(path: string): Promise<ImportResult> {
+  return invoke<ImportResult>("import_local_archive", { path });
+}
+
+export async function importLocalDirectory(path: string): Promise<ImportResult> {
+  return invoke<ImportResult>("import_local_directory", { path });
+}
+
+export async function checkRepoUpdates(repoKey: string): Promise<RepoUpdateStatus> {
+  return invoke<RepoUpdateStatus>("check_repo_updates", { repoKey });
+}
+
+export async function refreshRepo(repoKey: string): Promise<RefreshResult> {
+  return invoke<RefreshResult>("refresh_repo", { repoKey });
+}
+
+export async function cancelImport(repoKey: string): Promise<boolean> {
+  return invoke<boolean>("cancel_import", { repoKey });
+}
+
+export async function onImportProgress(
+  handler: (progress: DownloadProgress) => void
+): Promise<UnlistenFn> {
+  return listen<DownloadProgress>("import-progress", (event) => handler(event.payload));
+}
+
+export async function startBatchImport(urls: string[]): Promise<BatchStatus> {
+  return invoke<BatchStatus>("start_batch_import", { urls });
+}
+
+export async function importAllFavorites(): Promise<BatchStatus> {
+  return invoke<BatchStatus>("import_all_favorites");
+}
+
+export async function getBatchImportStatus(batchId: string): Promise<BatchStatus | null> {
+  return invoke<BatchStatus | null>("get_batch_import_status", { batchId });
+}
+
+export async function cancelBatchImport(batchId: string): Promise<boolean> {
+  return invoke<boolean>("cancel_batch_import", { batchId });
+}
+
+export async function onBatchImportProgress(
+  handler: (status: BatchStatus) => void
+): Promise<UnlistenFn> {
+  return listen<BatchStatus>("batch-import-progress", (event) => handler(event.payload));
+}
+
+export async function isLayoutMigrationRunning(): Promise<boolean> {
+  return invoke<boolean>("is_layout_migration_running");
+}
+
+export async function onLayoutMigrationFinished(handler: () => void): Promise<UnlistenFn> {
+  return listen("layout-migration-finished", () => handler());
+}
+
 export async function readTextFile(repoKey: string, filePath: string): Promise<FileContent> {
   return invoke<FileContent>("read_text_file", { repoKey, filePath });
 }
 
-export async function listRecentRepos(): Promise<RepoInfo[]> {
-  return invoke<RepoInfo[]>("list_recent_repos");
+export async function fetchLfsObject(repoKey: string, filePath: string): Promise<FileContent> {
+  return invoke<FileContent>("fetch_lfs_object", { repoKey, filePath });
+}
+
+export async function listRecentRepos(): Promise<RepoGroup[]> {
+  return invoke<RepoGroup[]>("list_recent_repos");
 }
 
+/** The root node of a repo's tree; folders are listed with getTreeChildren */
 export async function getRepoTree(repoKey: string): Promise<FileNode> {
   return invoke<FileNode>("get_repo_tree", { repoKey });
 }
 
+export async function getTreeChildren(repoKey: string, path: string, offset: number, limit: number): Promise<TreePage> {
+  return invoke<TreePage>("get_tree_children", { repoKey, path, offset, limit });
+}
+
+export async function listRepoFiles(repoKey: string): Promise<string[]> {
+  return invoke<string[]>("list_repo_files", { repoKey });
+}
+
 export async function getRepoInfo(repoKey: string): Promise<RepoInfo> {
   return invoke<RepoInfo>("get_repo_info", { repoKey });
 }
@@ -42,8 +122,20 @@ export async function getFileLanguage(filePath: string): Promise<string> {
   return invoke<string>("get_file_language", { filePath });
 }
 
-export async function searchGithubRepos(query: string, token?: string | null): Promise<SearchResultItem[]> {
-  return invoke<SearchResultItem[]>("search_github_repos", { query, token });
+export async function searchGithubRepos(
+  query: string,
+  token?: string | null
+): Promise<Cached<SearchResultItem[]>> {
+  return invoke<Cached<SearchResultItem[]>>("search_github_repos", { query, token });
+}
+
+export async function searchRepos(
+  provider: ProviderKind,
+  host: string | null,
+  query: string,
+  token?: string | null
+): Promise<Cached<SearchResultItem[]>> {
+  return invoke<Cached<SearchResultItem[]>>("search_repos", { provider, host, query, token });
 }
 
 export async function getSettings(): Promise<AppSettings> {
@@ -66,8 +158,8 @@ export async function getTrendingRepos(
   language: string | null,
   since: string,
   spokenLanguage: string | null
-): Promise<TrendingRepo[]> {
-  return invoke<TrendingRepo[]>("get_trending_repos", {
+): Promise<Cached<TrendingRepo[]>> {
+  return invoke<Cached<TrendingRepo[]>>("get_trending_repos", {
     language,
     since,
     spokenLanguage,
@@ -129,7 +221,8 @@ export async function createGist(
   filename: string,
   content: string,
   description: string,
-  isPublic: boolean
+  isPublic: boolean,
+  host?: string | null
 ): Promise<CreateGistResult> {
   return invoke<CreateGistResult>("create_gist", {
     token,
@@ -137,6 +230,7 @@ export async function createGist(
     content,
     description,
     public: isPublic,
+    host: host ?? null,
   });
 }
 
@@ -159,3 +253,28 @@ export async function deleteChatSession(repoUrl: string, sessionId: string): Pro
 export async function updateRepoLastOpened(repoKey: string): Promise<void> {
   return invoke<void>("update_repo_last_opened", { repoKey });
 }
+
+export async function setRepoPinned(repoKey: string, pinned: boolean): Promise<void> {
+  return invoke<void>("set_repo_pinned", { repoKey, pinned });
+}
+
+export async function setRepoIgnorePatterns(repoKey: string, patterns: string[]): Promise<FileNode> {
+  return invoke<FileNode>("set_repo_ignore_patterns", { repoKey, patterns });
+}
+
+export async function getStorageUsage(): Promise<StorageUsage> {
+  return invoke<StorageUsage>("get_storage_usage");
+}
+
+export async function getStorageLocations(): Promise<StorageLocations> {
+  return invoke<StorageLocations>("get_storage_locations");
+}
+
+/** Unset folders mean the platform defaults; `portable` overrides both */
+export async function moveStorage(
+  dataDir: string | null,
+  configDir: string | null,
+  portable: boolean
+): Promise<StorageLocations> {
+  return invoke<StorageLocations>("move_storage", { dataDir, configDir, portable });
+}
@@ -20,6 +20,7 @@ interface CodeViewerProps {
   onScreenshotSaved?: (copiedToClipboard: boolean) => void;
   onCaptureComplete?: () => void;
   onToast?: (message: string) => void;
+  onFetchLfs?: () => Promise<void>;
 }
 
 export interface CodeViewerHandle {
@@ -183,8 +184,10 @@ export const CodeViewer = forwardRef<CodeViewerHandle, CodeViewerProps>(function
   onScreenshotSaved,
   onCaptureComplete,
   onToast,
+  onFetchLfs,
 }, ref) {
   const [showPreview, setShowPreview] = useState(false);
+  const [fetchingLfs, setFetchingLfs] = useState(false);
   const [editorReady, setEditorReady] = useState(false);
   const [copiedUrl, setCopiedUrl] = useState(false);
   const codeContentRef = useRef<HTMLDivElement>(null);
@@ -226,14 +229,40 @@ export const CodeViewer = forwardRef<CodeViewerHandle, CodeViewerProps>(function
     filePath.endsWith(".md") || filePath.endsWith(".markdown");
   const fileUrls = useMemo(() => {
     if (!repoInfo || !filePath) return null;
-    const encodedPath = filePath
+    // Local directories and archives have no hosted copy to link to
+    if (repoInfo.source && repoInfo.source !== "remote") return null;
+    // Subfolder imports store paths relative to that folder
+    const repoPath = repoInfo.subpath ? `${repoInfo.subpath}/${filePath}` : filePath;
+    const encodedPath = repoPath
       .split("/")
       .map((segment) => encodeURIComponent(segment))
       .join("/");
-    return {
-      githubUrl: `https://github.com/${repoInfo.owner}/${repoInfo.repo}/blob/${repoInfo.branch}/${encodedPath}`,
-      rawUrl: `https://raw.githubusercontent.com/${repoInfo.owner}/${repoInfo.repo}/${repoInfo.branch}/${encodedPath}`,
-    };
+    const base = `https://${repoInfo.host ?? "github.com"}/${repoInfo.owner}/${repoInfo.repo}`;
+    const ref = repoInfo.branch;
+    switch (repoInfo.provider) {
+      case "gitlab":
+        return {
+          githubUrl: `${base}/-/blob/${ref}/${encodedPath}`,
+          rawUrl: `${base}/-/raw/${ref}/${encodedPath}`,
+        };
+      case "gitea": {
+        const refPath = `${repoInfo.ref_kind ?? "branch"}/${ref}`;
+        return {
+          githubUrl: `${base}/src/${refPath}/${encodedPath}`,
+          rawUrl: `${base}/raw/${refPath}/${encodedPath}`,
+        };
+      }
+      case "bitbucket":
+        return {
+          githubUrl: `${base}/src/${ref}/${encodedPath}`,
+          rawUrl: `${base}/raw/${ref}/${encodedPath}`,
+        };
+      default:
+        return {
+          githubUrl: `${base}/blob/${ref}/${encodedPath}`,
+          rawUrl: `https://raw.githubusercontent.com/${repoInfo.owner}/${repoInfo.repo}/${ref}/${encodedPath}`,
+        };
+    }
   }, [repoInfo, filePath]);
 
   const handleOpenInGitHub = async () => {
@@ -467,6 +496,30 @@ export const CodeViewer = forwardRef<CodeViewerHandle, CodeViewerProps>(function
           </div>
         )}
       </div>
+      {content.lfs_pointer && !content.lfs_fetched && (
+        <div className="lfs-banner">
+          <span>
+            This is a Git LFS pointer. The real file is{" "}
+            {(content.lfs_pointer.size / 1024).toLocaleString(undefined, { maximumFractionDigits: 1 })} KB.
+          </span>
+          {onFetchLfs && (
+            <button
+              className="lfs-fetch-button"
+              disabled={fetchingLfs}
+              onClick={async () => {
+                setFetchingLfs(true);
+                try {
+                  await onFetchLfs();
+                } finally {
+                  setFetchingLfs(false);
+                }
+              }}
+            >
+              {fetchingLfs ? "Fetching..." : "Fetch from LFS"}
+            </button>
+          )}
+        </div>
+      )}
       <div className="code-content" ref={codeContentRef}>
         {isMarkdown && showPreview ? (
           <MarkdownPreview content={content.content} />
@@ -1,11 +1,12 @@
 import { useState, useEffect, useCallback, useRef, useMemo } from "react";
-import type { FileNode, FileContent } from "../types";
+import type { FileContent } from "../types";
 import "./ContentSearch.css";
 
 interface ContentSearchProps {
   isOpen: boolean;
   onClose: () => void;
-  tree: FileNode | null;
+  /** Every file path in the repo; null while loading */
+  files: string[] | null;
   readFileContent: (path: string) => Promise<FileContent>;
   onResultSelect: (path: string, line: number) => void;
 }
@@ -24,16 +25,8 @@ interface SearchResult {
   truncated: boolean;
 }
 
-function flattenTree(node: FileNode, files: FlatFile[] = []): FlatFile[] {
-  if (!node.is_dir) {
-    files.push({ path: node.path, name: node.name });
-  }
-  if (node.children) {
-    for (const child of node.children) {
-      flattenTree(child, files);
-    }
-  }
-  return files;
+function toFlatFiles(paths: string[]): FlatFile[] {
+  return paths.map((path) => ({ path, name: path.slice(path.lastIndexOf("/") + 1) }));
 }
 
 function getFileIcon(name: string): string {
@@ -86,7 +79,7 @@ function truncateLine(text: string, maxLength = 220): string {
 export function ContentSearch({
   isOpen,
   onClose,
-  tree,
+  files,
   readFileContent,
   onResultSelect,
 }: ContentSearchProps) {
@@ -111,9 +104,9 @@ export function ContentSearch({
   const DEBOUNCE_MS = 250;
 
   const allFiles = useMemo(() => {
-    if (!tree) return [];
-    return flattenTree(tree);
-  }, [tree]);
+    if (!files) return [];
+    return toFlatFiles(files);
+  }, [files]);
 
   useEffect(() => {
     if (!isOpen) {
@@ -143,7 +136,7 @@ export function ContentSearch({
     setLimited(false);
     lastCompletedQueryRef.current = "";
     cacheRef.current.clear();
-  }, [tree?.path]);
+  }, [files]);
 
   useEffect(() => {
     if (!listRef.current) return;
@@ -1,11 +1,11 @@
 import { useState, useEffect, useCallback, useRef, useMemo } from "react";
-import type { FileNode } from "../types";
 import "./FileSearch.css";
 
 interface FileSearchProps {
   isOpen: boolean;
   onClose: () => void;
-  tree: FileNode | null;
+  /** Every file path in the repo; null while loading */
+  files: string[] | null;
   onFileSelect: (path: string) => void;
 }
 
@@ -14,17 +14,9 @@ interface FlatFile {
   name: string;
 }
 
-// Flatten file tree to get all files (not directories)
-function flattenTree(node: FileNode, files: FlatFile[] = []): FlatFile[] {
-  if (!node.is_dir) {
-    files.push({ path: node.path, name: node.name });
-  }
-  if (node.children) {
-    for (const child of node.children) {
-      flattenTree(child, files);
-    }
-  }
-  return files;
+// Pair each file path with its name
+function toFlatFiles(paths: string[]): FlatFile[] {
+  return paths.map((path) => ({ path, name: path.slice(path.lastIndexOf("/") + 1) }));
 }
 
 // Simple fuzzy match: check if all query chars appear in order in the target
@@ -65,17 +57,16 @@ function fuzzyMatch(query: string, target: string): { match: boolean; score: num
   return { match: queryIndex === queryLower.length, score };
 }
 
-export function FileSearch({ isOpen, onClose, tree, onFileSelect }: FileSearchProps) {
+export function FileSearch({ isOpen, onClose, files, onFileSelect }: FileSearchProps) {
   const [query, setQuery] = useState("");
   const [selectedIndex, setSelectedIndex] = useState(0);
   const inputRef = useRef<HTMLInputElement>(null);
   const listRef = useRef<HTMLDivElement>(null);
 
-  // Get all files from tree
   const allFiles = useMemo(() => {
-    if (!tree) return [];
-    return flattenTree(tree);
-  }, [tree]);
+    if (!files) return [];
+    return toFlatFiles(files);
+  }, [files]);
 
   // Filter and sort files based on query
   const filteredFiles = useMemo(() => {
@@ -186,7 +177,7 @@ export function FileSearch({ isOpen, onClose, tree, onFileSelect }: FileSearchPr
         <div className="file-search-list" ref={listRef}>
           {filteredFiles.length === 0 ? (
             <div className="file-search-empty">
-              {query ? "No files found" : "No files in repository"}
+              {!files ? "Loading files..." : query ? "No files found" : "No files in repository"}
             </div>
           ) : (
             filteredFiles.map((file, index) => (
@@ -1,8 +1,19 @@
 import { useState, useCallback, useMemo, useEffect, useRef } from "react";
+import { getTreeChildren } from "../api";
 import type { FileNode } from "../types";
 
+// Folders are listed this many entries at a time
+const PAGE_SIZE = 500;
+// Filtering stops adding files after this many matches
+const MAX_FILTER_MATCHES = 1000;
+
 interface FileTreeProps {
+  repoKey: string;
+  /** Root node; folder contents are fetched as they are expanded */
   tree: FileNode;
+  /** Every file path in the repo, for filtering; null until requested */
+  files: string[] | null;
+  onRequestFiles: () => void;
   onFileSelect: (path: string) => void;
   selectedPath?: string;
   revealPath?: string;
@@ -13,6 +24,12 @@ interface FileTreeProps {
   canNavigateForward?: boolean;
 }
 
+/** The loaded part of a folder's children */
+interface LoadedChildren {
+  children: FileNode[];
+  total: number;
+}
+
 interface TreeNodeProps {
   node: FileNode;
   depth: number;
@@ -20,10 +37,22 @@ interface TreeNodeProps {
   selectedPath?: string;
   expandedPaths: Set<string>;
   onToggle: (path: string) => void;
+  childrenOf: (node: FileNode) => LoadedChildren | undefined;
+  onLoadMore: (path: string) => void;
   forceExpand?: boolean;
 }
 
-function TreeNode({ node, depth, onFileSelect, selectedPath, expandedPaths, onToggle, forceExpand }: TreeNodeProps) {
+function TreeNode({
+  node,
+  depth,
+  onFileSelect,
+  selectedPath,
+  expandedPaths,
+  onToggle,
+  childrenOf,
+  onLoadMore,
+  forceExpand,
+}: TreeNodeProps) {
   const expanded = forceExpand ? true : expandedPaths.has(node.path);
   const isSelected = selectedPath === node.path;
 
@@ -76,68 +105,139 @@ function TreeNode({ node, depth, onFileSelect, selectedPath, expandedPaths, onTo
         onClick={handleClick}
       >
         <span className="tree-icon">{getFileIcon(node.name, node.is_dir)}</span>
-        <span className="tree-name">{node.name}</span>
+        <span className="tree-name" title={describeNode(node)}>
+          {node.name}
+        </span>
+        {node.classification && <span className="tree-size tree-class">{node.classification}</span>}
         {!node.is_dir && node.size !== undefined && (
-          <span className="tree-size">{formatSize(node.size)}</span>
+          <span className="tree-size">
+            {node.line_count !== undefined && `${node.line_count.toLocaleString()} lines · `}
+            {formatSize(node.size)}
+          </span>
+        )}
+        {node.is_dir && node.total_size !== undefined && (
+          <span className="tree-size">{formatSize(node.total_size)}</span>
+        )}
+        {node.submodule && (
+          <span
+            className="tree-size"
+            title={`${node.submodule.url}${node.submodule.commit_sha ? ` @ ${node.submodule.commit_sha}` : ""}`}
+          >
+            {node.submodule.populated ? "submodule" : "submodule (not fetched)"}
+          </span>
         )}
       </div>
-      {node.is_dir && expanded && node.children && (
+      {node.is_dir && expanded && (
         <div className="tree-children">
-          {node.children.map((child) => (
-            <TreeNode
-              key={child.path}
-              node={child}
-              depth={depth + 1}
-              onFileSelect={onFileSelect}
-              selectedPath={selectedPath}
-              expandedPaths={expandedPaths}
-              onToggle={onToggle}
-              forceExpand={forceExpand}
-            />
-          ))}
+          <TreeChildren
+            loaded={childrenOf(node)}
+            path={node.path}
+            depth={depth + 1}
+            onFileSelect={onFileSelect}
+            selectedPath={selectedPath}
+            expandedPaths={expandedPaths}
+            onToggle={onToggle}
+            childrenOf={childrenOf}
+            onLoadMore={onLoadMore}
+            forceExpand={forceExpand}
+          />
         </div>
       )}
     </div>
   );
 }
 
+interface TreeChildrenProps extends Omit<TreeNodeProps, "node"> {
+  loaded: LoadedChildren | undefined;
+  path: string;
+}
+
+function TreeChildren({ loaded, path, depth, onLoadMore, ...nodeProps }: TreeChildrenProps) {
+  const indent = { paddingLeft: `${depth * 16 + 8}px` };
+  if (!loaded) {
+    return (
+      <div className="tree-item tree-more" style={indent}>
+        Loading...
+      </div>
+    );
+  }
+
+  const remaining = loaded.total - loaded.children.length;
+  return (
+    <>
+      {loaded.children.map((child) => (
+        <TreeNode key={child.path} node={child} depth={depth} onLoadMore={onLoadMore} {...nodeProps} />
+      ))}
+      {remaining > 0 && (
+        <div className="tree-item tree-more" style={indent} onClick={() => onLoadMore(path)}>
+          Show {Math.min(remaining, PAGE_SIZE)} more of {remaining}
+        </div>
+      )}
+    </>
+  );
+}
+
+// Tooltip with what the tree knows about a node beyond its name
+function describeNode(node: FileNode): string {
+  const details: string[] = [node.path];
+  if (node.language) details.push(node.language);
+  if (node.is_binary) details.push("binary");
+  if (node.file_count !== undefined) {
+    details.push(`${node.file_count.toLocaleString()} ${node.file_count === 1 ? "file" : "files"}`);
+  }
+  return details.join(" · ");
+}
+
 function formatSize(bytes: number): string {
   if (bytes < 1024) return `${bytes} B`;
   if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(1)} KB`;
   return `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
 }
 
-// Collect all directory paths from the tree
-function collectDirPaths(node: FileNode, paths: string[] = []): string[] {
-  if (node.is_dir) {
-    paths.push(node.path);
-    if (node.children) {
-      for (const child of node.children) {
-        collectDirPaths(child, paths);
-      }
-    }
+function sortNodes(nodes: FileNode[]) {
+  nodes.sort((a, b) => {
+    if (a.is_dir !== b.is_dir) return a.is_dir ? -1 : 1;
+    return a.name.toLowerCase().localeCompare(b.name.toLowerCase());
+  });
+  for (const node of nodes) {
+    if (node.children) sortNodes(node.children);
   }
-  return paths;
 }
 
-function filterTreeNode(node: FileNode, query: string): FileNode | null {
-  const match = node.name.toLowerCase().includes(query);
-  if (!node.is_dir) {
-    return match ? node : null;
-  }
+// Build the folders and files whose names match the query, with their ancestors
+function buildFilteredTree(files: string[], query: string): FileNode[] {
+  const root: FileNode = { name: "", path: "", is_dir: true, children: [] };
+  const dirs = new Map<string, FileNode>([["", root]]);
 
-  const children = node.children || [];
-  const filteredChildren = children
-    .map((child) => filterTreeNode(child, query))
-    .filter((child): child is FileNode => Boolean(child));
+  const ensureDir = (parts: string[]): FileNode => {
+    const path = parts.join("/");
+    let dir = dirs.get(path);
+    if (!dir) {
+      dir = { name: parts[parts.length - 1], path, is_dir: true, children: [] };
+      dirs.set(path, dir);
+      ensureDir(parts.slice(0, -1)).children!.push(dir);
+    }
+    return dir;
+  };
 
-  if (match || filteredChildren.length > 0) {
-    return {
-      ...node,
-      children: filteredChildren,
-    };
+  let matches = 0;
+  for (const path of files) {
+    if (matches >= MAX_FILTER_MATCHES) break;
+    const parts = path.split("/");
+    for (let i = 1; i < parts.length; i++) {
+      if (parts[i - 1].toLowerCase().includes(query)) {
+        ensureDir(parts.slice(0, i));
+      }
+    }
+    const name = parts[parts.length - 1];
+    if (name.toLowerCase().includes(query)) {
+      ensureDir(parts.slice(0, -1)).children!.push({ name, path, is_dir: false });
+      matches++;
+    }
   }
-  return null;
+
+  sortNodes(root.children!);
+  return root.children!;
 }
 
 function buildAncestorPaths(path: string): string[] {
@@ -158,7 +258,10 @@ function getSafeSelector(path: string): string {
 }
 
 export function FileTree({
+  repoKey,
   tree,
+  files,
+  onRequestFiles,
   onFileSelect,
   selectedPath,
   revealPath,
@@ -170,22 +273,66 @@ export function FileTree({
 }: FileTreeProps) {
   const [expandedPaths, setExpandedPaths] = useState<Set<string>>(new Set());
   const [filterText, setFilterText] = useState("");
+  const [loaded, setLoaded] = useState<Map<string, LoadedChildren>>(new Map());
+  const loadingRef = useRef<Set<string>>(new Set());
   const treeContentRef = useRef<HTMLDivElement>(null);
   const handledRevealRequestIdRef = useRef(0);
+  const pendingRevealRef = useRef<string | null>(null);
 
   useEffect(() => {
     setFilterText("");
   }, [tree.path, tree.name]);
 
-  const allDirPaths = useMemo(() => {
-    const paths: string[] = [];
-    if (tree.children) {
-      for (const child of tree.children) {
-        collectDirPaths(child, paths);
+  // A new tree starts over, since the folders may have changed
+  useEffect(() => {
+    setLoaded(new Map());
+    loadingRef.current = new Set();
+  }, [repoKey, tree]);
+
+  const loadChildren = useCallback(
+    async (path: string, offset: number) => {
+      const request = `${path}\n${offset}`;
+      if (loadingRef.current.has(request)) return;
+      const requests = loadingRef.current;
+      requests.add(request);
+      try {
+        const page = await getTreeChildren(repoKey, path, offset, PAGE_SIZE);
+        // Drop pages for a tree that has since been replaced
+        if (loadingRef.current !== requests) return;
+        setLoaded((prev) => {
+          const next = new Map(prev);
+          const before = offset > 0 ? prev.get(path)?.children ?? [] : [];
+          next.set(path, { children: [...before, ...page.children], total: page.total });
+          return next;
+        });
+      } catch (err) {
+        console.error("Failed to load folder:", err);
+        // Shown empty rather than retried, e.g. for a folder gone after a rebuild
+        if (loadingRef.current === requests && offset === 0) {
+          setLoaded((prev) => new Map(prev).set(path, { children: [], total: 0 }));
+        }
+      } finally {
+        requests.delete(request);
+      }
+    },
+    [repoKey]
+  );
+
+  // The root and every expanded folder get their first page
+  useEffect(() => {
+    for (const path of ["", ...expandedPaths]) {
+      if (!loaded.has(path)) {
+        loadChildren(path, 0);
       }
     }
-    return paths;
-  }, [tree]);
+  }, [expandedPaths, loaded, loadChildren]);
+
+  const handleLoadMore = useCallback(
+    (path: string) => {
+      loadChildren(path, loaded.get(path)?.children.length ?? 0);
+    },
+    [loaded, loadChildren]
+  );
 
   const handleToggle = useCallback((path: string) => {
     setExpandedPaths((prev) => {
@@ -199,9 +346,18 @@ export function FileTree({
     });
   }, []);
 
+  // Expands every folder loaded so far, so each click opens one more level
   const handleExpandAll = useCallback(() => {
-    setExpandedPaths(new Set(allDirPaths));
-  }, [allDirPaths]);
+    setExpandedPaths((prev) => {
+      const next = new Set(prev);
+      for (const { children } of loaded.values()) {
+        for (const child of children) {
+          if (child.is_dir) next.add(child.path);
+        }
+      }
+      return next;
+    });
+  }, [loaded]);
 
   const handleCollapseAll = useCallback(() => {
     setExpandedPaths(new Set());
@@ -210,13 +366,26 @@ export function FileTree({
   const filterQuery = filterText.trim().toLowerCase();
   const isFiltering = filterQuery.length > 0;
 
+  useEffect(() => {
+    if (isFiltering && !files) onRequestFiles();
+  }, [isFiltering, files, onRequestFiles]);
+
   const filteredChildren = useMemo(() => {
-    if (!tree.children) return [];
-    if (!isFiltering) return tree.children;
-    return tree.children
-      .map((child) => filterTreeNode(child, filterQuery))
-      .filter((child): child is FileNode => Boolean(child));
-  }, [tree.children, filterQuery, isFiltering]);
+    if (!isFiltering || !files) return [];
+    return buildFilteredTree(files, filterQuery);
+  }, [files, filterQuery, isFiltering]);
+
+  // Filter results come whole; otherwise children are what has been loaded
+  const childrenOf = useCallback(
+    (node: FileNode): LoadedChildren | undefined => {
+      if (isFiltering) {
+        const children = node.children ?? [];
+        return { children, total: children.length };
+      }
+      return loaded.get(node.path);
+    },
+    [isFiltering, loaded]
+  );
 
   useEffect(() => {
     if (!revealPath || !revealRequestId) return;
@@ -232,14 +401,30 @@ export function FileTree({
       return next;
     });
 
-    const selector = `[data-path="${getSafeSelector(revealPath)}"]`;
+    // Scrolled to once its folders have loaded
+    pendingRevealRef.current = revealPath;
+  }, [revealPath, revealRequestId]);
+
+  useEffect(() => {
+    const pending = pendingRevealRef.current;
+    if (!pending) return;
+    const selector = `[data-path="${getSafeSelector(pending)}"]`;
     requestAnimationFrame(() => {
       const target = treeContentRef.current?.querySelector(selector);
-      target?.scrollIntoView({ block: "nearest" });
+      if (target) {
+        target.scrollIntoView({ block: "nearest" });
+        pendingRevealRef.current = null;
+      }
     });
-  }, [revealPath, revealRequestId]);
+  }, [loaded, expandedPaths]);
 
-  if (!tree.children || tree.children.length === 0) {
+  const rootChildren = isFiltering
+    ? files
+      ? { children: filteredChildren, total: filteredChildren.length }
+      : undefined
+    : loaded.get("");
+
+  if (tree.child_count === 0) {
     return <div className="file-tree empty">No files</div>;
   }
 
@@ -265,7 +450,7 @@ export function FileTree({
           >
             ▶
           </button>
-          <button className="tree-action-btn" onClick={handleExpandAll} title="Expand All">
+          <button className="tree-action-btn" onClick={handleExpandAll} title="Expand Loaded Folders">
             ⊞
           </button>
           <button className="tree-action-btn" onClick={handleCollapseAll} title="Collapse All">
@@ -274,18 +459,18 @@ export function FileTree({
         </div>
       </div>
       <div className="tree-content" ref={treeContentRef}>
-        {filteredChildren.map((child) => (
-          <TreeNode
-            key={child.path}
-            node={child}
-            depth={0}
-            onFileSelect={onFileSelect}
-            selectedPath={selectedPath}
-            expandedPaths={expandedPaths}
-            onToggle={handleToggle}
-            forceExpand={isFiltering}
-          />
-        ))}
+        <TreeChildren
+          loaded={rootChildren}
+          path=""
+          depth={0}
+          onFileSelect={onFileSelect}
+          selectedPath={selectedPath}
+          expandedPaths={expandedPaths}
+          onToggle={handleToggle}
+          childrenOf={childrenOf}
+          onLoadMore={handleLoadMore}
+          forceExpand={isFiltering}
+        />
       </div>
       <div className="tree-filter">
         <input
@@ -1,13 +1,15 @@
 import { useState, useEffect, useRef } from "react";
 import { revealItemInDir } from "@tauri-apps/plugin-opener";
 import { getRepoPath } from "../api";
-import type { RepoInfo } from "../types";
+import type { RepoGroup, RepoInfo } from "../types";
 
 interface RepoListProps {
-  repos: RepoInfo[];
+  groups: RepoGroup[];
   onSelect: (repo: RepoInfo) => void;
   onDelete: (repoKey: string) => void;
   onUpdate: (repoKey: string) => void;
+  onTogglePin: (repo: RepoInfo) => void;
+  onEditIgnore: (repo: RepoInfo) => void;
   updatingRepoKey?: string | null;
 }
 
@@ -39,7 +41,7 @@ function formatRelativeTime(isoString: string): string {
   });
 }
 
-export function RepoList({ repos, onSelect, onDelete, onUpdate, updatingRepoKey }: RepoListProps) {
+export function RepoList({ groups, onSelect, onDelete, onUpdate, onTogglePin, onEditIgnore, updatingRepoKey }: RepoListProps) {
   const [contextMenu, setContextMenu] = useState<ContextMenuState>({
     visible: false,
     x: 0,
@@ -92,7 +94,19 @@ export function RepoList({ repos, onSelect, onDelete, onUpdate, updatingRepoKey
     setContextMenu((prev) => ({ ...prev, visible: false }));
   };
 
-  if (repos.length === 0) {
+  const menuRepo = groups.flatMap((group) => group.refs).find((repo) => repo.key === contextMenu.repoKey);
+
+  const handleTogglePinFromMenu = () => {
+    if (menuRepo) onTogglePin(menuRepo);
+    setContextMenu((prev) => ({ ...prev, visible: false }));
+  };
+
+  const handleEditIgnoreFromMenu = () => {
+    if (menuRepo) onEditIgnore(menuRepo);
+    setContextMenu((prev) => ({ ...prev, visible: false }));
+  };
+
+  if (groups.length === 0) {
     return null;
   }
 
@@ -100,7 +114,9 @@ export function RepoList({ repos, onSelect, onDelete, onUpdate, updatingRepoKey
     <div className="repo-list">
       <h3>Recent Repositories</h3>
       <div className="repo-items">
-        {repos.map((repo) => {
+        {groups.map((group) => (
+        <div key={group.refs[0].key} className="repo-group">
+        {group.refs.map((repo) => {
           const isUpdating = updatingRepoKey === repo.key;
           return (
           <div
@@ -115,6 +131,16 @@ export function RepoList({ repos, onSelect, onDelete, onUpdate, updatingRepoKey
               <span className="repo-name">
                 {repo.owner}/{repo.repo}
               </span>
+              {repo.branch && (
+                <span className="repo-branch">
+                  {repo.ref_kind === "commit" ? repo.branch.slice(0, 7) : repo.branch}
+                </span>
+              )}
+              {repo.pinned && (
+                <span className="repo-pinned" title="Kept when storage is over its limit">
+                  Pinned
+                </span>
+              )}
               {isUpdating && <span className="repo-updating">Updating...</span>}
               <span className="repo-date">
                 {formatRelativeTime(repo.last_opened_at || repo.imported_at)}
@@ -135,6 +161,8 @@ export function RepoList({ repos, onSelect, onDelete, onUpdate, updatingRepoKey
           </div>
           );
         })}
+        </div>
+        ))}
       </div>
 
       {/* Context Menu */}
@@ -153,6 +181,12 @@ export function RepoList({ repos, onSelect, onDelete, onUpdate, updatingRepoKey
           <button className="context-menu-item" onClick={handleUpdateFromMenu}>
             Update
           </button>
+          <button className="context-menu-item" onClick={handleTogglePinFromMenu}>
+            {menuRepo?.pinned ? "Unpin" : "Pin"}
+          </button>
+          <button className="context-menu-item" onClick={handleEditIgnoreFromMenu}>
+            Ignore Rules...
+          </button>
           <div className="context-menu-divider" />
           <button
             className="context-menu-item danger"
@@ -3,17 +3,81 @@ export interface FileNode {
   path: string;
   is_dir: boolean;
   size?: number;
+  is_symlink?: boolean;
+  symlink_target?: string;
+  hash?: string;
+  submodule?: SubmoduleInfo;
+  lfs_pointer?: LfsPointer;
+  /** Detected language of a file; for a directory, the language most of its code is in */
+  language?: string;
+  /** Lines in a text file */
+  line_count?: number;
+  is_binary?: boolean;
+  classification?: FileClass;
+  /** Bytes in all files below a directory */
+  total_size?: number;
+  /** Files below a directory, at any depth */
+  file_count?: number;
+  /** Number of entries in a directory, also set when `children` isn't loaded */
+  child_count?: number;
   children?: FileNode[];
 }
 
+/** One page of a folder's children; child folders come without their children */
+export interface TreePage {
+  path: string;
+  offset: number;
+  total: number;
+  children: FileNode[];
+}
+
+/** Why a file is usually not worth reading */
+export type FileClass = "generated" | "vendored" | "minified";
+
+export interface LfsPointer {
+  oid: string;
+  size: number;
+}
+
+export interface SubmoduleInfo {
+  url: string;
+  commit_sha: string | null;
+  populated: boolean;
+}
+
+export type RefKind = "branch" | "tag" | "commit";
+
+export type ProviderKind = "github" | "gitlab" | "gitea" | "bitbucket";
+
+export type SourceKind = "remote" | "local_directory" | "local_archive";
+
 export interface RepoInfo {
   key: string;
   owner: string;
   repo: string;
+  source: SourceKind;
+  provider: ProviderKind;
+  host: string;
   branch: string;
+  ref_kind: RefKind;
+  commit_sha?: string | null;
+  subpath?: string | null;
   imported_at: string;
   url: string;
   last_opened_at?: string;
+  /** Kept when the cache is over its quota */
+  pinned?: boolean;
+  /** Gitignore-style patterns for this repo only, overriding its ignore files and the global patterns */
+  ignore_patterns?: string[];
+}
+
+/** All cached refs of one repository, most recently opened first */
+export interface RepoGroup {
+  provider: ProviderKind;
+  host: string;
+  owner: string;
+  repo: string;
+  refs: RepoInfo[];
 }
 
 export interface ImportResult {
@@ -22,12 +86,62 @@ export interface ImportResult {
   tree: FileNode;
 }
 
+export interface RepoUpdateStatus {
+  repo_key: string;
+  current_sha: string | null;
+  latest_sha: string | null;
+  has_update: boolean;
+}
+
+export interface TreeChanges {
+  added: string[];
+  removed: string[];
+  modified: string[];
+}
+
+export interface RefreshResult extends ImportResult {
+  previous_sha: string | null;
+  changes: TreeChanges;
+}
+
+export interface DownloadProgress {
+  repo_key: string;
+  url: string;
+  received_bytes: number;
+  total_bytes: number | null;
+}
+
 export interface FileContent {
   content: string;
   truncated: boolean;
   total_lines?: number;
   language: string;
   is_binary: boolean;
+  lfs_pointer?: LfsPointer;
+  lfs_fetched?: boolean;
+}
+
+export type BatchItemState = "queued" | "running" | "retrying" | "done" | "failed" | "cancelled";
+
+export interface BatchItemStatus {
+  url: string;
+  state: BatchItemState;
+  attempts: number;
+  repo_key: string | null;
+  error: string | null;
+}
+
+export interface BatchStatus {
+  batch_id: string;
+  items: BatchItemStatus[];
+  finished: boolean;
+}
+
+/** Data that may come from the response cache; `stale` means the network was unavailable */
+export interface Cached<T> {
+  data: T;
+  stale: boolean;
+  fetched_at: string;
 }
 
 export interface SearchResultItem {
@@ -45,6 +159,64 @@ export interface AppSettings {
   openrouter_api_key: string | null;
   interpret_prompt: string;
   interpret_model: string;
+  max_extract_size_mb: number;
+  max_extract_entries: number;
+  fetch_submodules: boolean;
+  max_concurrent_imports: number;
+  github_hosts: GitHubHost[];
+  proxy_url: string | null;
+  no_proxy: string | null;
+  connect_timeout_secs: number;
+  read_timeout_secs: number;
+  storage_mode: StorageMode;
+  /** Repo cache size limit; the least recently opened repos are evicted beyond it */
+  cache_quota_mb: number | null;
+  /** Gitignore-style patterns hidden from every file tree */
+  ignore_patterns: string[];
+  respect_gitignore: boolean;
+  /** Hide paths marked `linguist-vendored` in `.gitattributes` */
+  hide_vendored: boolean;
+}
+
+/** Disk space of one cached repo; `shared_bytes` is blob store content other repos may also use */
+export interface RepoUsage {
+  key: string;
+  owner: string;
+  repo: string;
+  branch: string;
+  size_bytes: number;
+  shared_bytes: number;
+  imported_at: string;
+  last_opened_at: string | null;
+  pinned: boolean;
+  favorite: boolean;
+}
+
+/** Folders the app keeps its data and config in; `portable` keeps both next to the executable */
+export interface StorageLocations {
+  data_dir: string;
+  config_dir: string;
+  portable: boolean;
+}
+
+export interface StorageUsage {
+  /** Largest first */
+  repos: RepoUsage[];
+  blob_store_bytes: number;
+  total_bytes: number;
+  quota_bytes: number | null;
+}
+
+/** `archive` reads files out of the kept zip; `deduplicated` stores file contents once across repos */
+export type StorageMode = "extracted" | "archive" | "deduplicated";
+
+/** A GitHub Enterprise Server instance; unset URLs use the defaults for `host` */
+export interface GitHubHost {
+  host: string;
+  web_url?: string | null;
+  api_url?: string | null;
+  archive_url?: string | null;
+  token?: string | null;
 }
 
 export interface TrendingRepo {